@@ -0,0 +1,304 @@
+use std::ops::RangeInclusive;
+use egui::{ plot::{ Legend, Line, Plot, PlotPoints, HLine, LineStyle }, Color32 };
+
+use crate::Judged;
+
+const JUDGE_COLORS: [Color32; 5] = [
+    Color32::RED,
+    Color32::BLUE,
+    Color32::GREEN,
+    Color32::GOLD,
+    Color32::from_rgb(200, 0, 200),
+];
+
+pub fn median(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mid = sorted.len() / 2;
+    match sorted.len() % 2 {
+        0 => (sorted[mid - 1] + sorted[mid]) / 2.0,
+        _ => sorted[mid],
+    }
+}
+
+/// Each judge's mark minus the panel median for that element.
+pub fn deviations(judged: &Judged) -> [[f32; 10]; 5] {
+    let medians: [f32; 10] = std::array::from_fn(|i| median(&judged.execution_5.map(|marks| marks[i])));
+    judged.execution_5.map(|marks| std::array::from_fn(|i| marks[i] - medians[i]))
+}
+
+/// Marks are whole tenths, but their f32 differences are not: 0.5 - 0.3 is 0.19999999.
+pub fn is_outlier(deviation: f32, threshold: f32) -> bool {
+    deviation.abs() + 1e-4 >= threshold
+}
+
+/// The day a routine was judged, used to group routines into sessions.
+pub fn session(judged: &Judged) -> String {
+    judged.date_of_creation.chars().take(10).collect()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JudgeStats {
+    /// Mean signed deviation, positive means the judge deducts more than the panel.
+    pub bias: f32,
+    pub mean_abs: f32,
+    pub outliers: usize,
+    pub marks: usize,
+}
+
+pub struct Outlier {
+    pub date: String,
+    pub athlete: String,
+    pub element: usize,
+    pub judge: usize,
+    pub mark: f32,
+    pub median: f32,
+}
+
+pub fn judge_stats(judged: &[&Judged], threshold: f32) -> [JudgeStats; 5] {
+    let mut stats = [JudgeStats::default(); 5];
+    for j in judged {
+        let deviations = deviations(j);
+        for k in 0..5 {
+            for d in deviations[k] {
+                stats[k].bias += d;
+                stats[k].mean_abs += d.abs();
+                stats[k].marks += 1;
+                if is_outlier(d, threshold) {
+                    stats[k].outliers += 1;
+                }
+            }
+        }
+    }
+    for s in stats.iter_mut() {
+        if s.marks > 0 {
+            s.bias /= s.marks as f32;
+            s.mean_abs /= s.marks as f32;
+        }
+    }
+    stats
+}
+
+pub fn outliers(judged: &[&Judged], threshold: f32) -> Vec<Outlier> {
+    let mut outliers = vec![];
+    for j in judged {
+        let deviations = deviations(j);
+        for (k, row) in deviations.iter().enumerate() {
+            for (i, deviation) in row.iter().enumerate() {
+                if is_outlier(*deviation, threshold) {
+                    outliers.push(Outlier {
+                        date: j.date_of_creation.clone(),
+                        athlete: j.athlete.clone(),
+                        element: i + 1,
+                        judge: k + 1,
+                        mark: j.execution_5[k][i],
+                        median: j.execution_5[k][i] - deviation,
+                    });
+                }
+            }
+        }
+    }
+    outliers
+}
+
+pub struct JudgeReport {
+    pub open: bool,
+    /// `None` reports on every session.
+    session: Option<String>,
+    threshold: f32,
+}
+
+impl JudgeReport {
+    pub fn new() -> JudgeReport {
+        JudgeReport {
+            open: false,
+            session: None,
+            threshold: 0.2,
+        }
+    }
+
+    pub fn display(&mut self, egui_ctx: &egui::Context, judged: &[Judged]) {
+        let mut sessions = judged
+            .iter()
+            .filter(|j| j.five_judges)
+            .map(session)
+            .collect::<Vec<String>>();
+        sessions.sort();
+        sessions.dedup();
+
+        egui::Window
+            ::new("Judge Consistency")
+            .open(&mut self.open)
+            .scroll2([false, true])
+            .show(egui_ctx, |ui| {
+                if sessions.is_empty() {
+                    ui.label("No routines have been judged by five judges yet");
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Session: ");
+                    egui::ComboBox
+                        ::from_id_source("judge report session")
+                        .selected_text(self.session.clone().unwrap_or("All".to_owned()))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.session, None, "All");
+                            for s in sessions.iter() {
+                                ui.selectable_value(&mut self.session, Some(s.clone()), s);
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Outlier threshold ");
+                    ui.add(
+                        egui::DragValue
+                            ::new(&mut self.threshold)
+                            .speed(0.05)
+                            .clamp_range(0.05..=0.5)
+                            .fixed_decimals(2)
+                    );
+                });
+                ui.separator();
+
+                let selected = judged
+                    .iter()
+                    .filter(|j| j.five_judges)
+                    .filter(|j| match &self.session {
+                        Some(s) => &session(j) == s,
+                        None => true,
+                    })
+                    .collect::<Vec<&Judged>>();
+
+                ui.label(format!("{} routines", selected.len()));
+                egui::Grid
+                    ::new("judge stats")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Judge");
+                        ui.label("Bias");
+                        ui.label("Mean deviation");
+                        ui.label("Outliers");
+                        ui.end_row();
+                        for (k, s) in judge_stats(&selected, self.threshold).iter().enumerate() {
+                            ui.colored_label(JUDGE_COLORS[k], format!("J{}", k + 1));
+                            ui.label(format!("{:+.3}", s.bias)).on_hover_text(
+                                "positive deducts more than the panel"
+                            );
+                            ui.label(format!("{:.3}", s.mean_abs));
+                            ui.label(format!("{} / {}", s.outliers, s.marks));
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.collapsing("Outlier marks", |ui| {
+                    for o in outliers(&selected, self.threshold) {
+                        ui.label(
+                            format!(
+                                "{} {} element {}: J{} gave {:.1} (median {:.1})",
+                                o.date,
+                                o.athlete,
+                                o.element,
+                                o.judge,
+                                o.mark,
+                                o.median
+                            )
+                        );
+                    }
+                });
+
+                ui.separator();
+                ui.label("Bias per session");
+                fn point_label(p: f64, _range: &RangeInclusive<f64>) -> String {
+                    format!("{p}")
+                }
+                let by_session = sessions
+                    .iter()
+                    .map(|s| {
+                        let routines = judged
+                            .iter()
+                            .filter(|j| j.five_judges && &session(j) == s)
+                            .collect::<Vec<&Judged>>();
+                        judge_stats(&routines, self.threshold)
+                    })
+                    .collect::<Vec<[JudgeStats; 5]>>();
+                let labels = sessions.clone();
+                Plot::new("judge bias")
+                    .x_axis_formatter(point_label)
+                    .label_formatter(move |name, p| {
+                        match labels.get(p.x.round() as usize) {
+                            Some(s) => format!("{} {} {:+.3}", name, s, p.y),
+                            None => format!("{} {:+.3}", name, p.y),
+                        }
+                    })
+                    .height(200.0)
+                    .legend(Legend::default())
+                    .show(ui, |plot_ui| {
+                        plot_ui.hline(
+                            HLine::new(0.0).color(Color32::GRAY).style(LineStyle::Dashed { length: 5.0 })
+                        );
+                        for k in 0..5 {
+                            let points: PlotPoints = by_session
+                                .iter()
+                                .enumerate()
+                                .map(|(i, s)| [i as f64, s[k].bias as f64])
+                                .collect();
+                            plot_ui.line(
+                                Line::new(points)
+                                    .color(JUDGE_COLORS[k])
+                                    .name(format!("J{}", k + 1))
+                            );
+                        }
+                    });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panel(marks: [f32; 5]) -> Judged {
+        let mut judged = Judged::new();
+        judged.five_judges = true;
+        for (k, mark) in marks.iter().enumerate() {
+            judged.execution_5[k][0] = *mark;
+        }
+        judged
+    }
+
+    #[test]
+    fn median_of_odd_and_even() {
+        assert_eq!(median(&[]), 0.0);
+        assert_eq!(median(&[0.3, 0.1, 0.2]), 0.2);
+        assert_eq!(median(&[0.4, 0.1, 0.2, 0.3]), 0.25);
+    }
+
+    #[test]
+    fn deviations_from_panel_median() {
+        let deviations = deviations(&panel([0.1, 0.2, 0.2, 0.3, 0.5]));
+        let first = deviations.map(|d| (d[0] * 10.0).round() / 10.0);
+        assert_eq!(first, [-0.1, 0.0, 0.0, 0.1, 0.3]);
+        assert!(deviations.iter().all(|d| d[1..].iter().all(|x| *x == 0.0)));
+    }
+
+    #[test]
+    fn threshold_is_inclusive_in_tenths() {
+        assert!(is_outlier(0.3 - 0.1, 0.2));
+        assert!(is_outlier(0.5 - 0.3, 0.2));
+        assert!(is_outlier(0.3 - 0.5, 0.2));
+        assert!(!is_outlier(0.4 - 0.3, 0.2));
+        assert!(is_outlier(0.1, 0.05));
+    }
+
+    #[test]
+    fn outliers_at_the_boundary() {
+        let judged = panel([0.3, 0.3, 0.3, 0.1, 0.5]);
+        let found = outliers(&[&judged], 0.2);
+        assert_eq!(found.iter().map(|o| o.judge).collect::<Vec<_>>(), [4, 5]);
+        let stats = judge_stats(&[&judged], 0.2);
+        assert_eq!(stats.map(|s| s.outliers), [0, 0, 0, 1, 1]);
+    }
+}
@@ -0,0 +1,257 @@
+use std::path::Path;
+use ffmpeg_sidecar::event::FfmpegEvent;
+
+use crate::decoder::ffmpeg;
+use crate::sequence::Source;
+use crate::strobe::save_png;
+
+/// Colour of annotations, on screen and in exported frames.
+pub const ANNOTATION_COLOR: [u8; 4] = [173, 255, 47, 255];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Line,
+    Angle,
+    Circle,
+    Freehand,
+}
+
+/// Points are fractions of the frame size, like `TrackPoint`.
+#[derive(Debug, Clone, Savefile, PartialEq)]
+pub enum Shape {
+    Line([f32; 2], [f32; 2]),
+    /// Two arms meeting at the middle point.
+    Angle([f32; 2], [f32; 2], [f32; 2]),
+    Circle {
+        centre: [f32; 2],
+        edge: [f32; 2],
+    },
+    Freehand(Vec<[f32; 2]>),
+}
+
+/// A drawing on the frame shown at `time` seconds.
+#[derive(Debug, Clone, Savefile, PartialEq)]
+pub struct Annotation {
+    pub time: f32,
+    pub shape: Shape,
+}
+
+/// Angle at `vertex` in degrees, measured in pixels of a `width` by `height` frame.
+pub fn angle(a: [f32; 2], vertex: [f32; 2], b: [f32; 2], width: f32, height: f32) -> f32 {
+    let u = ((a[0] - vertex[0]) * width, (a[1] - vertex[1]) * height);
+    let v = ((b[0] - vertex[0]) * width, (b[1] - vertex[1]) * height);
+    let cross = u.0 * v.1 - u.1 * v.0;
+    let dot = u.0 * v.0 + u.1 * v.1;
+    cross.abs().atan2(dot).to_degrees()
+}
+
+/// Direction of `p` seen from `from`, in radians, with y pointing down like the screen.
+fn direction(from: [f32; 2], p: [f32; 2], width: f32, height: f32) -> f32 {
+    ((p[1] - from[1]) * height).atan2((p[0] - from[0]) * width)
+}
+
+impl Shape {
+    pub fn describe(&self, width: f32, height: f32) -> String {
+        match self {
+            Shape::Line(a, b) => {
+                let length = (((b[0] - a[0]) * width).powi(2) + ((b[1] - a[1]) * height).powi(2)).sqrt();
+                format!("Line {:.0}px", length)
+            }
+            Shape::Angle(a, vertex, b) => format!("Angle {:.1}°", angle(*a, *vertex, *b, width, height)),
+            Shape::Circle { centre, edge } => {
+                let radius = (((edge[0] - centre[0]) * width).powi(2) + ((edge[1] - centre[1]) * height).powi(2)).sqrt();
+                format!("Circle r {:.0}px", radius)
+            }
+            Shape::Freehand(points) => format!("Freehand, {} points", points.len()),
+        }
+    }
+
+    /// The shape as line segments, with circles and the arc of angles flattened.
+    pub fn segments(&self, width: f32, height: f32) -> Vec<([f32; 2], [f32; 2])> {
+        let arc = |centre: [f32; 2], radius: f32, from: f32, to: f32| {
+            let steps = 48;
+            (0..=steps)
+                .map(|i| {
+                    let t = from + (to - from) * (i as f32) / (steps as f32);
+                    [centre[0] + (radius * t.cos()) / width, centre[1] + (radius * t.sin()) / height]
+                })
+                .collect::<Vec<[f32; 2]>>()
+        };
+        let polyline = |points: &[[f32; 2]]| points.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>();
+        match self {
+            Shape::Line(a, b) => vec![(*a, *b)],
+            Shape::Angle(a, vertex, b) => {
+                let mut segments = vec![(*vertex, *a), (*vertex, *b)];
+                let start = direction(*vertex, *a, width, height);
+                let mut end = direction(*vertex, *b, width, height);
+                // the short way round, the side the angle is measured on
+                if end - start > std::f32::consts::PI {
+                    end -= std::f32::consts::TAU;
+                } else if start - end > std::f32::consts::PI {
+                    end += std::f32::consts::TAU;
+                }
+                segments.extend(polyline(&arc(*vertex, width.min(height) * 0.04, start, end)));
+                segments
+            }
+            Shape::Circle { centre, edge } => {
+                let radius = (((edge[0] - centre[0]) * width).powi(2) + ((edge[1] - centre[1]) * height).powi(2)).sqrt();
+                polyline(&arc(*centre, radius, 0.0, std::f32::consts::TAU))
+            }
+            Shape::Freehand(points) => polyline(points),
+        }
+    }
+}
+
+/// Draws a line `thickness` pixels wide into an RGBA image.
+fn rasterize(image: &mut [u8], width: u32, height: u32, a: [f32; 2], b: [f32; 2], thickness: f32) {
+    let (ax, ay) = (a[0] * (width as f32), a[1] * (height as f32));
+    let (bx, by) = (b[0] * (width as f32), b[1] * (height as f32));
+    let steps = ((bx - ax).abs().max((by - ay).abs()) * 2.0).ceil().max(1.0) as usize;
+    let r = (thickness / 2.0).max(0.5);
+    for i in 0..=steps {
+        let t = (i as f32) / (steps as f32);
+        let (cx, cy) = (ax + (bx - ax) * t, ay + (by - ay) * t);
+        let (x0, x1) = ((cx - r).floor().max(0.0) as u32, ((cx + r).ceil() as u32).min(width));
+        let (y0, y1) = ((cy - r).floor().max(0.0) as u32, ((cy + r).ceil() as u32).min(height));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if ((x as f32) + 0.5 - cx).powi(2) + ((y as f32) + 0.5 - cy).powi(2) <= r * r {
+                    let i = ((y * width + x) * 4) as usize;
+                    image[i..i + 4].copy_from_slice(&ANNOTATION_COLOR);
+                }
+            }
+        }
+    }
+}
+
+/// 5 by 7 pixel glyphs for angle readouts, one row per byte with the leftmost pixel in bit 4.
+fn glyph(c: char) -> Option<[u8; 7]> {
+    Some(match c {
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        '.' => [0, 0, 0, 0, 0, 0b01100, 0b01100],
+        '°' => [0b01100, 0b10010, 0b10010, 0b01100, 0, 0, 0],
+        _ => {
+            return None;
+        }
+    })
+}
+
+/// Writes `text` into an RGBA image just above and right of `position`, with a dark
+/// outline so it reads on any background. Only digits, `.` and `°` are drawn.
+fn draw_label(image: &mut [u8], width: u32, height: u32, position: [f32; 2], text: &str) {
+    // about the size of a thirtieth of the frame height
+    let scale = (((height as f32) / 30.0 / 7.0).round() as i64).max(1);
+    let border = (scale / 2).max(1);
+    let left = ((position[0] * (width as f32)) as i64) + (height as i64) / 60;
+    let top = ((position[1] * (height as f32)) as i64) - (height as i64) / 60 - 7 * scale;
+    let mut fill = |x0: i64, y0: i64, size: i64, color: [u8; 4]| {
+        for y in y0.max(0)..(y0 + size).min(height as i64) {
+            for x in x0.max(0)..(x0 + size).min(width as i64) {
+                let i = ((y * (width as i64) + x) * 4) as usize;
+                image[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    };
+    let glyphs = text.chars().filter_map(glyph).collect::<Vec<[u8; 7]>>();
+    for (color, grow) in [([0, 0, 0, 255], border), (ANNOTATION_COLOR, 0)] {
+        for (n, rows) in glyphs.iter().enumerate() {
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0b10000 >> column) != 0 {
+                        let x = left + ((n as i64) * 6 + column) * scale;
+                        let y = top + (row as i64) * scale;
+                        fill(x - grow, y - grow, scale + grow * 2, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The frame at `time` as RGBA.
+fn decode_frame(source: &Source, time: f32, long_edge: Option<u32>) -> Result<(u32, u32, Vec<u8>), String> {
+    let filter = match long_edge {
+        Some(edge) => format!("scale=w='if(gte(iw,ih),{0},-1)':h='if(lt(iw,ih),{0},-1)'", edge),
+        None => "null".to_owned(),
+    };
+    let mut frame = None;
+    let mut errors = vec![];
+    source
+        .input(ffmpeg().seek(time.to_string()))
+        .args(["-vf", &filter])
+        .args(["-frames:v", "1", "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .spawn()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map_err(|e| e.to_string())?
+        .for_each(|event| {
+            match event {
+                FfmpegEvent::OutputFrame(f) => {
+                    frame = Some((f.width, f.height, f.data));
+                }
+                FfmpegEvent::Error(e) => errors.push(e),
+                _ => {}
+            }
+        });
+    frame.ok_or(match errors.is_empty() {
+        true => format!("No frame at {:.2}sec", time),
+        false => errors.join("\n"),
+    })
+}
+
+/// Saves the frame at `time` with its annotations drawn on as a PNG. `size` is the
+/// source frame size, which angles are measured in.
+pub fn export_annotated(
+    source: &Source,
+    time: f32,
+    shapes: &[Shape],
+    size: (f32, f32),
+    long_edge: Option<u32>,
+    path: &Path
+) -> Result<(), String> {
+    let (width, height, mut image) = decode_frame(source, time, long_edge)?;
+    let thickness = ((width.max(height) as f32) / 400.0).max(2.0);
+    for shape in shapes {
+        for (a, b) in shape.segments(width as f32, height as f32) {
+            rasterize(&mut image, width, height, a, b, thickness);
+        }
+        if let Shape::Angle(a, vertex, b) = shape {
+            draw_label(&mut image, width, height, *vertex, &format!("{:.1}°", angle(*a, *vertex, *b, size.0, size.1)));
+        }
+    }
+    save_png(path, width, height, image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn angles_in_pixels() {
+        assert!((angle([1.0, 0.5], [0.5, 0.5], [0.5, 0.0], 100.0, 100.0) - 90.0).abs() < 1e-3);
+        // the same fractions on a wide frame are no longer square
+        assert!((angle([1.0, 0.5], [0.5, 0.5], [1.0, 0.0], 200.0, 100.0) - 26.565).abs() < 1e-2);
+    }
+
+    #[test]
+    fn labels_are_drawn_and_clipped() {
+        let (width, height) = (320, 240);
+        let mut image = vec![0; width * height * 4];
+        draw_label(&mut image, width as u32, height as u32, [0.5, 0.5], "90.0°");
+        let lit = image.chunks_exact(4).filter(|p| *p == ANNOTATION_COLOR).count();
+        assert!(lit > 0);
+        // off every edge without panicking
+        for position in [[0.0, 0.0], [0.99, 0.99], [-1.0, 2.0], [2.0, -1.0]] {
+            draw_label(&mut image, width as u32, height as u32, position, "123.4°");
+        }
+    }
+}
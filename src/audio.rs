@@ -0,0 +1,244 @@
+use std::io::Read;
+use ffmpeg_sidecar::event::FfmpegEvent;
+
+use crate::decoder::{ ffmpeg, Timeline };
+use crate::detect::{ percentile, snap_to_frames, Suggestion, MIN_FLIGHT };
+use crate::sequence::Source;
+
+const SAMPLE_RATE: usize = 8000;
+/// Loudness samples per second, fine enough to place contacts within a frame.
+pub const ENVELOPE_RATE: usize = 400;
+/// Envelope samples a rise in loudness is measured over, 10ms.
+const RISE: usize = ENVELOPE_RATE / 100;
+/// Onsets closer together than this are one impact ringing on.
+const MIN_ONSET_SPACING: f32 = 0.05;
+/// How far from the noise floor to the sharpest rise an onset has to reach.
+const ONSET_THRESHOLD: f32 = 0.3;
+
+/// Loudness of the audio track, `ENVELOPE_RATE` RMS values per second from the start
+/// of the file, so it lines up with the video even when the audio starts later.
+pub fn audio_envelope(source: &Source) -> Result<Vec<f32>, String> {
+    let path = match source {
+        Source::File(path) => path,
+        Source::Images { .. } => {
+            return Err("Image sequences have no audio".to_owned());
+        }
+    };
+    let mut child = ffmpeg()
+        .input(path)
+        .no_video()
+        // pad with silence up to the first audio sample, as frame times count from the file start
+        .args(["-af", "aresample=async=1:first_pts=0"])
+        .args(["-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-f", "f32le", "-"])
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let mut stdout = child.take_stdout().ok_or("ffmpeg has no output")?;
+    // read on another thread so ffmpeg never blocks on a full stderr pipe
+    let reader = std::thread::spawn(move || {
+        let mut bytes = vec![];
+        stdout.read_to_end(&mut bytes).map(|_| bytes)
+    });
+    let mut errors = vec![];
+    child
+        .iter()
+        .map_err(|e| e.to_string())?
+        .for_each(|event| {
+            if let FfmpegEvent::Error(e) = event {
+                errors.push(e);
+            }
+        });
+    let bytes = match reader.join() {
+        Ok(Ok(bytes)) => bytes,
+        Ok(Err(e)) => {
+            return Err(e.to_string());
+        }
+        Err(_) => {
+            return Err("Error reading audio".to_owned());
+        }
+    };
+    let samples = bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect::<Vec<f32>>();
+    if samples.is_empty() {
+        return Err(match errors.is_empty() {
+            true => format!("No audio track in {}", path),
+            false => errors.join("\n"),
+        });
+    }
+    Ok(
+        samples
+            .chunks(SAMPLE_RATE / ENVELOPE_RATE)
+            .map(|window| (window.iter().map(|s| s * s).sum::<f32>() / (window.len() as f32)).sqrt())
+            .collect()
+    )
+}
+
+/// Envelope indices where a sharp rise in loudness starts, with its strength from 0 to 1.
+/// Steady noise doesn't rise, so music or a loud hall doesn't hide the impacts.
+fn onsets(envelope: &[f32]) -> Vec<(usize, f32)> {
+    if envelope.len() <= RISE {
+        return vec![];
+    }
+    let flux = (0..envelope.len())
+        .map(|i| match i >= RISE {
+            true => (envelope[i] - envelope[i - RISE]).max(0.0),
+            false => 0.0,
+        })
+        .collect::<Vec<f32>>();
+    let floor = percentile(&flux, 0.5);
+    let peak = flux.iter().cloned().fold(0.0, f32::max);
+    if (peak - floor).abs() < f32::EPSILON {
+        return vec![];
+    }
+    let threshold = floor + (peak - floor) * ONSET_THRESHOLD;
+    let spacing = ((MIN_ONSET_SPACING * (ENVELOPE_RATE as f32)) as usize).max(1);
+    let mut found: Vec<(usize, f32)> = vec![];
+    for i in RISE..flux.len() {
+        let window = &flux[i.saturating_sub(spacing)..(i + spacing + 1).min(flux.len())];
+        if flux[i] < threshold || window.iter().any(|f| *f > flux[i]) {
+            continue;
+        }
+        // walk back to the quiet sample the rise starts from
+        let mut start = i;
+        while start > 0 && envelope[start - 1] < envelope[start] {
+            start -= 1;
+        }
+        match found.last() {
+            Some((last, _)) if start < last + spacing => {}
+            _ => found.push((start, (flux[i] - floor) / (peak - floor))),
+        }
+    }
+    found
+}
+
+/// Where the loudness after the onset at `start` falls back halfway to `floor`.
+fn decay(envelope: &[f32], start: usize, floor: f32) -> usize {
+    let end = (start + ((MIN_FLIGHT * (ENVELOPE_RATE as f32)) as usize)).min(envelope.len());
+    let (peak_at, peak) = envelope[start..end]
+        .iter()
+        .enumerate()
+        .fold((0, floor), |best, (i, e)| match *e > best.1 {
+            true => (i, *e),
+            false => best,
+        });
+    let half = floor + (peak - floor) / 2.0;
+    match envelope[start + peak_at..].iter().position(|e| *e < half) {
+        Some(i) => start + peak_at + i,
+        None => envelope.len() - 1,
+    }
+}
+
+/// Take-offs and landings from the impacts heard in `envelope`. Every contact starts
+/// with the landing impact, and onsets less than a flight apart are the same contact,
+/// the last one being the bed letting go at take-off. A contact heard as a single impact
+/// takes off where its sound has died down halfway. Returns alternating take-offs and
+/// landings like `detect_contacts`, snapped to the nearest frame of `timeline`.
+pub fn detect_audio_contacts(envelope: &[f32], timeline: &Timeline) -> Vec<Suggestion> {
+    let rate = ENVELOPE_RATE as f32;
+    let mut contacts: Vec<Vec<(usize, f32)>> = vec![];
+    for onset in onsets(envelope) {
+        match contacts.last_mut() {
+            Some(contact) if ((onset.0 - contact[contact.len() - 1].0) as f32) / rate < MIN_FLIGHT => {
+                contact.push(onset);
+            }
+            _ => contacts.push(vec![onset]),
+        }
+    }
+    let floor = match envelope.is_empty() {
+        true => 0.0,
+        false => percentile(envelope, 0.2),
+    };
+    let mut suggestions = vec![];
+    for pair in contacts.windows(2) {
+        let take_off = match pair[0].len() {
+            1 => (decay(envelope, pair[0][0].0, floor), pair[0][0].1 / 2.0),
+            n => pair[0][n - 1],
+        };
+        let landing = pair[1][0];
+        if ((landing.0 - take_off.0.min(landing.0)) as f32) / rate >= MIN_FLIGHT {
+            suggestions.push(Suggestion { time: (take_off.0 as f32) / rate, confidence: take_off.1 });
+            suggestions.push(Suggestion { time: (landing.0 as f32) / rate, confidence: landing.1 });
+        }
+    }
+    snap_to_frames(suggestions, timeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `seconds` of loudness at `level` with impacts of `(time, loudness)` that rise over 5ms
+    /// and die away over about 50ms.
+    fn envelope(seconds: f32, level: f32, impacts: &[(f32, f32)]) -> Vec<f32> {
+        let rate = ENVELOPE_RATE as f32;
+        (0..((seconds * rate) as usize))
+            .map(|i| {
+                let t = (i as f32) / rate;
+                level +
+                    impacts
+                        .iter()
+                        .filter(|(at, _)| t >= *at)
+                        .map(|(at, loudness)| loudness * ((t - at) / 0.005).min(1.0) * (-(t - at) / 0.05).exp())
+                        .sum::<f32>()
+            })
+            .collect()
+    }
+
+    fn times(suggestions: &[Suggestion]) -> Vec<f32> {
+        suggestions.iter().map(|s| s.time).collect()
+    }
+
+    fn assert_near(found: &[f32], expected: &[f32], tolerance: f32) {
+        assert_eq!(found.len(), expected.len(), "{:?}", found);
+        for (f, e) in found.iter().zip(expected) {
+            assert!((f - e).abs() <= tolerance, "{:?} vs {:?}", found, expected);
+        }
+    }
+
+    fn timeline() -> Timeline {
+        Timeline::Fixed { fps: 100.0, frames: 1000 }
+    }
+
+    #[test]
+    fn landings_and_releases() {
+        // landing, then the bed letting go 0.2sec later
+        let impacts = [(0.5, 1.0), (0.7, 0.5), (1.8, 1.0), (2.0, 0.5), (3.1, 1.0), (3.3, 0.5)];
+        let suggestions = detect_audio_contacts(&envelope(4.0, 0.01, &impacts), &timeline());
+        assert_near(&times(&suggestions), &[0.7, 1.8, 2.0, 3.1], 0.01);
+        assert!(suggestions.iter().all(|s| s.confidence > 0.3));
+    }
+
+    #[test]
+    fn impacts_over_steady_noise() {
+        // louder than the impacts, which loudness alone could not see past
+        let impacts = [(0.5, 0.4), (0.7, 0.2), (1.8, 0.4), (2.0, 0.2)];
+        let suggestions = detect_audio_contacts(&envelope(3.0, 0.8, &impacts), &timeline());
+        assert_near(&times(&suggestions), &[0.7, 1.8], 0.01);
+    }
+
+    #[test]
+    fn single_impacts_take_off_as_they_die_away() {
+        let impacts = [(0.5, 1.0), (1.8, 1.0), (3.1, 1.0)];
+        let suggestions = detect_audio_contacts(&envelope(4.0, 0.01, &impacts), &timeline());
+        // halfway down from the peak 5ms in, 0.05 * ln 2 later
+        let release = 0.005 + 0.035;
+        assert_near(&times(&suggestions), &[0.5 + release, 1.8, 1.8 + release, 3.1], 0.015);
+    }
+
+    #[test]
+    fn snapped_to_frames() {
+        let impacts = [(0.503, 1.0), (0.703, 0.5), (1.803, 1.0)];
+        let suggestions = detect_audio_contacts(&envelope(3.0, 0.01, &impacts), &Timeline::Fixed { fps: 10.0, frames: 30 });
+        assert_eq!(times(&suggestions), vec![0.7, 1.8]);
+    }
+
+    #[test]
+    fn nothing_in_silence() {
+        assert!(detect_audio_contacts(&envelope(2.0, 0.0, &[]), &timeline()).is_empty());
+        assert!(detect_audio_contacts(&envelope(2.0, 0.3, &[]), &timeline()).is_empty());
+        assert!(detect_audio_contacts(&[], &timeline()).is_empty());
+        // one contact is no flight
+        assert!(detect_audio_contacts(&envelope(2.0, 0.01, &[(0.5, 1.0)]), &timeline()).is_empty());
+    }
+}
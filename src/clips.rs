@@ -0,0 +1,89 @@
+use std::path::Path;
+use ffmpeg_sidecar::event::FfmpegEvent;
+
+use crate::decoder::ffmpeg;
+use crate::sequence::Source;
+
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub name: String,
+    pub start: f32,
+    pub end: f32,
+}
+
+/// One clip per take-off and landing pair in `points`, named after `names` where there is one.
+pub fn skill_clips(points: &[f32], names: &[String], padding: f32) -> Vec<Clip> {
+    points
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(i, pair)| Clip {
+            name: format!("{:02} {}", i + 1, match names.get(i) {
+                Some(name) => name.clone(),
+                None => format!("Element {}", i + 1),
+            }),
+            start: (pair[0] - padding).max(0.0),
+            end: pair[1] + padding,
+        })
+        .collect()
+}
+
+/// The whole routine, from the first take-off to the last landing.
+pub fn routine_clip(points: &[f32], name: &str, padding: f32) -> Option<Clip> {
+    let first = points.first()?;
+    let last = points.last()?;
+    if last <= first {
+        return None;
+    }
+    Some(Clip {
+        name: name.to_owned(),
+        start: (first - padding).max(0.0),
+        end: last + padding,
+    })
+}
+
+/// Skill names contain characters like `/`, keep file names to what every platform accepts.
+pub fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_alphanumeric() || c == ' ' || c == '-' || c == '.' {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+pub fn export_clip(video: &Source, clip: &Clip, folder: &Path) -> Result<(), String> {
+    let output = folder.join(format!("{}.mp4", file_name(&clip.name)));
+    let mut errors = vec![];
+    let mut child = video
+        .input(ffmpeg().seek(clip.start.to_string()))
+        .duration((clip.end - clip.start).to_string())
+        .codec_video("libx264")
+        .codec_audio("aac")
+        .overwrite()
+        .output(output.display().to_string())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    child
+        .iter()
+        .map_err(|e| e.to_string())?
+        .for_each(|event| {
+            if let FfmpegEvent::Error(e) = event {
+                errors.push(e);
+            }
+        });
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(format!("{}: {}", clip.name, errors.join("\n"))),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Exports every clip into `folder`, returning how many were written.
+pub fn export_clips(video: &Source, clips: &[Clip], folder: &Path) -> Result<usize, String> {
+    for clip in clips {
+        export_clip(video, clip, folder)?;
+    }
+    Ok(clips.len())
+}
@@ -0,0 +1,255 @@
+use macroquad::prelude::*;
+
+use crate::video::Video;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareMode {
+    SideBySide,
+    Overlay,
+}
+
+/// One of the two compared videos and the take-off it is aligned on.
+struct Side {
+    /// Id of the `Video` window.
+    video: String,
+    /// Index into the video's `points`, always a take-off.
+    take_off: usize,
+}
+
+/// Plays two videos together, aligned so their chosen take-offs happen at the same time.
+pub struct VideoComparison {
+    pub open: bool,
+    sides: [Side; 2],
+    pub mode: CompareMode,
+    /// Opacity of the second video in overlay mode.
+    blend: f32,
+    /// Seconds since the aligned take-off, negative before it.
+    position: f32,
+    playing: bool,
+    speed: f32,
+    rect: [f32; 4],
+}
+
+impl VideoComparison {
+    pub fn new() -> VideoComparison {
+        VideoComparison {
+            open: false,
+            sides: [
+                Side { video: String::new(), take_off: 0 },
+                Side { video: String::new(), take_off: 0 },
+            ],
+            mode: CompareMode::SideBySide,
+            blend: 0.5,
+            position: 0.0,
+            playing: false,
+            speed: 1.0,
+            rect: [0.0; 4],
+        }
+    }
+
+    fn take_off_time(video: &Video, take_off: usize) -> f32 {
+        video.points.get(take_off).cloned().unwrap_or(0.0)
+    }
+
+    /// One frame of whichever video has more of them.
+    fn frame_step(a: &Video, b: &Video) -> f32 {
+        1.0 / a.timeline.fps().max(b.timeline.fps())
+    }
+
+    /// The videos being compared, when both are loaded.
+    fn pair<'a>(&self, videos: &'a mut [Video]) -> Option<(&'a mut Video, &'a mut Video)> {
+        let a = videos.iter().position(|v| v.id() == self.sides[0].video && v.info.is_some())?;
+        let b = videos.iter().position(|v| v.id() == self.sides[1].video && v.info.is_some())?;
+        if a == b {
+            return None;
+        }
+        let (low, high) = videos.split_at_mut(a.max(b));
+        let (first, second) = (&mut low[a.min(b)], &mut high[0]);
+        match a < b {
+            true => Some((first, second)),
+            false => Some((second, first)),
+        }
+    }
+
+    /// Range of `position` where both videos have frames.
+    fn range(&self, a: &Video, b: &Video) -> (f32, f32) {
+        let align_a = Self::take_off_time(a, self.sides[0].take_off);
+        let align_b = Self::take_off_time(b, self.sides[1].take_off);
+        let end = |v: &Video| v.time(v.frame_count() - 1);
+        (-align_a.min(align_b), (end(a) - align_a).min(end(b) - align_b))
+    }
+
+    /// Moves both videos to `position`, playing it forward if needed.
+    pub fn update(&mut self, videos: &mut [Video]) {
+        if !self.open {
+            return;
+        }
+        let sides = [self.sides[0].take_off, self.sides[1].take_off];
+        let playing = self.playing;
+        let step = get_frame_time() * self.speed;
+        let (min, max) = match self.pair(videos) {
+            Some((a, b)) => self.range(a, b),
+            None => {
+                return;
+            }
+        };
+        let (a, b) = self.pair(videos).unwrap();
+        if playing && a.frame_ready() && b.frame_ready() {
+            self.position += step;
+            if self.position > max {
+                self.position = min;
+            }
+        }
+        self.position = self.position.clamp(min, max.max(min));
+        for (video, take_off) in [(a, sides[0]), (b, sides[1])] {
+            video.playing = false;
+            let frame = video.frame_at(Self::take_off_time(video, take_off) + self.position);
+            if video.current_frame != frame {
+                video.seek(frame);
+            }
+            video.update_frames();
+        }
+    }
+
+    fn side_picker(ui: &mut egui::Ui, side: &mut Side, videos: &[Video], label: &str) {
+        ui.horizontal(|ui| {
+            egui::ComboBox
+                ::from_label(label)
+                .selected_text(match videos.iter().find(|v| v.id() == side.video) {
+                    Some(v) => v.path.clone(),
+                    None => "None".to_owned(),
+                })
+                .show_ui(ui, |ui| {
+                    for v in videos.iter().filter(|v| v.info.is_some()) {
+                        if ui.selectable_value(&mut side.video, v.id().to_owned(), &v.path).clicked() {
+                            side.take_off = 0;
+                        }
+                    }
+                });
+            if let Some(video) = videos.iter().find(|v| v.id() == side.video) {
+                egui::ComboBox
+                    ::from_id_source(format!("{} take-off", label))
+                    .selected_text(format!("take-off {}", side.take_off / 2 + 1))
+                    .show_ui(ui, |ui| {
+                        for (i, time) in video.points.iter().enumerate().step_by(2) {
+                            ui.selectable_value(&mut side.take_off, i, format!("take-off {} at {:.2}sec", i / 2 + 1, time));
+                        }
+                    });
+            }
+        });
+    }
+
+    pub fn display(&mut self, egui_ctx: &egui::Context, videos: &mut [Video]) {
+        let mut open = self.open;
+        egui::Window
+            ::new("Compare")
+            .open(&mut open)
+            .default_width(800.0)
+            .show(egui_ctx, |ui| {
+                Self::side_picker(ui, &mut self.sides[0], videos, "First");
+                Self::side_picker(ui, &mut self.sides[1], videos, "Second");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.mode, CompareMode::SideBySide, "side by side");
+                    ui.selectable_value(&mut self.mode, CompareMode::Overlay, "overlay");
+                    if self.mode == CompareMode::Overlay {
+                        ui.add(egui::Slider::new(&mut self.blend, 0.0..=1.0).text("blend"));
+                    }
+                });
+                ui.separator();
+
+                let (range, ratio, frame) = match self.pair(videos) {
+                    Some((a, b)) => {
+                        let ratio = |v: &Video| match v.info {
+                            Some(info) => (info.height as f32) / (info.width as f32),
+                            None => 1.0,
+                        };
+                        (self.range(a, b), ratio(a).max(ratio(b)), Self::frame_step(a, b))
+                    }
+                    None => {
+                        ui.label("Choose two loaded videos with marked take-offs");
+                        self.rect = [0.0; 4];
+                        return;
+                    }
+                };
+                let width = ui.available_width();
+                let height = match self.mode {
+                    CompareMode::SideBySide => (width / 2.0) * ratio,
+                    CompareMode::Overlay => width * ratio,
+                };
+                let r = ui.allocate_response(egui::vec2(width, height), egui::Sense::hover());
+                self.rect = [r.rect.left(), r.rect.top(), r.rect.width(), r.rect.height()];
+
+                ui.horizontal(|ui| {
+                    ui.small_button(egui_phosphor::SKIP_BACK).clicked().then(|| {
+                        self.playing = false;
+                        self.position -= frame;
+                    });
+                    let icon = match self.playing {
+                        true => egui_phosphor::PAUSE,
+                        false => egui_phosphor::PLAY,
+                    };
+                    ui.small_button(icon).clicked().then(|| {
+                        self.playing = !self.playing;
+                    });
+                    ui.small_button(egui_phosphor::SKIP_FORWARD).clicked().then(|| {
+                        self.playing = false;
+                        self.position += frame;
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut self.speed, 0.1..=2.0)
+                            .logarithmic(true)
+                            .fixed_decimals(2)
+                            .suffix("x")
+                    );
+                });
+                if
+                    ui
+                        .add(
+                            egui::Slider::new(&mut self.position, range.0..=range.1)
+                                .suffix("sec")
+                                .text("from take-off")
+                        )
+                        .changed()
+                {
+                    self.playing = false;
+                }
+            });
+        self.open = open;
+        if !self.open {
+            self.playing = false;
+            self.rect = [0.0; 4];
+        }
+    }
+
+    pub fn draw(&self, videos: &[Video], zoom: f32) {
+        if !self.open || self.rect[2] == 0.0 {
+            return;
+        }
+        let find = |side: &Side| videos.iter().find(|v| v.id() == side.video).and_then(|v| v.texture());
+        let (a, b) = match (find(&self.sides[0]), find(&self.sides[1])) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                return;
+            }
+        };
+        let [x, y, w, h] = self.rect.map(|v| v * zoom);
+        let fit = |texture: Texture2D, x: f32, w: f32, color: Color| {
+            let scale = (w / texture.width()).min(h / texture.height());
+            let size = vec2(texture.width() * scale, texture.height() * scale);
+            draw_texture_ex(texture, x + (w - size.x) / 2.0, y + (h - size.y) / 2.0, color, DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            });
+        };
+        match self.mode {
+            CompareMode::SideBySide => {
+                fit(a, x, w / 2.0, WHITE);
+                fit(b, x + w / 2.0, w / 2.0, WHITE);
+            }
+            CompareMode::Overlay => {
+                fit(a, x, w, WHITE);
+                fit(b, x, w, Color::new(1.0, 1.0, 1.0, self.blend));
+            }
+        }
+    }
+}
@@ -0,0 +1,699 @@
+use std::{
+    collections::HashMap,
+    sync::{ atomic::{ AtomicBool, AtomicU32, Ordering }, mpsc::{ self, TryRecvError }, Arc, Mutex, RwLock },
+    thread::JoinHandle,
+};
+use ffmpeg_sidecar::{ command::FfmpegCommand, event::{ FfmpegEvent, LogLevel } };
+
+use crate::sequence::Source;
+
+/// Default rate frames are resampled to, and the rate used for analysis.
+pub const FPS: f32 = 48.0;
+/// Default long edge of decoded frames.
+pub const LONG_EDGE: u32 = 720;
+
+/// ffmpeg binary chosen in the settings, empty for the one next to the app or on the PATH.
+static FFMPEG_PATH: RwLock<String> = RwLock::new(String::new());
+
+pub fn set_ffmpeg_path(path: &str) {
+    if let Ok(mut p) = FFMPEG_PATH.write() {
+        *p = path.trim().to_owned();
+    }
+}
+
+pub fn ffmpeg_path() -> String {
+    match FFMPEG_PATH.read() {
+        Ok(p) if !p.is_empty() => p.clone(),
+        _ => ffmpeg_sidecar::paths::ffmpeg_path().display().to_string(),
+    }
+}
+
+pub fn ffmpeg() -> FfmpegCommand {
+    FfmpegCommand::new_with_path(ffmpeg_path())
+}
+
+/// Set when the ffmpeg in use is older than 5.1, which only knows `-vsync`.
+static LEGACY_VSYNC: AtomicBool = AtomicBool::new(false);
+
+/// Arguments that keep every frame at its own timestamp, for the ffmpeg in use.
+pub fn passthrough() -> [&'static str; 2] {
+    match LEGACY_VSYNC.load(Ordering::Relaxed) {
+        true => ["-vsync", "passthrough"],
+        false => ["-fps_mode", "passthrough"],
+    }
+}
+
+/// Major and minor version in the first line of `ffmpeg -version`, `None` for
+/// builds from git or others without a release number.
+fn parse_version(line: &str) -> Option<(u32, u32)> {
+    let version = line.split("version ").nth(1)?.split_whitespace().next()?;
+    let mut numbers = version
+        .trim_start_matches('n')
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|n| n.parse::<u32>());
+    let major = numbers.next()?.ok()?;
+    let minor = numbers.next().and_then(|n| n.ok()).unwrap_or(0);
+    // dated builds like 2023-06-11-git-...
+    match major < 1000 {
+        true => Some((major, minor)),
+        false => None,
+    }
+}
+
+/// Set when ffmpeg was built without the `drawtext` filter, which burns in timestamps.
+static NO_DRAWTEXT: AtomicBool = AtomicBool::new(false);
+
+pub fn has_drawtext() -> bool {
+    !NO_DRAWTEXT.load(Ordering::Relaxed)
+}
+
+/// Features of the ffmpeg in use that are missing or worked around, for the settings.
+pub fn ffmpeg_notes() -> Vec<&'static str> {
+    let mut notes = vec![];
+    if LEGACY_VSYNC.load(Ordering::Relaxed) {
+        notes.push("Older than 5.1, using -vsync instead of -fps_mode");
+    }
+    if !has_drawtext() {
+        notes.push("No drawtext filter, timestamps can't be burnt in");
+    }
+    notes
+}
+
+/// Whether `ffmpeg -filters` lists `filter`.
+fn has_filter(path: &str, filter: &str) -> bool {
+    match std::process::Command::new(path).args(["-hide_banner", "-filters"]).output() {
+        Ok(output) =>
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.split_whitespace().nth(1) == Some(filter)),
+        Err(_) => false,
+    }
+}
+
+/// First line of `ffmpeg -version`, or why ffmpeg could not be run. Also finds out which
+/// of the options and filters the app uses this ffmpeg has, see `ffmpeg_notes`.
+pub fn ffmpeg_version() -> Result<String, String> {
+    let path = ffmpeg_path();
+    let output = std::process::Command
+        ::new(&path)
+        .arg("-version")
+        .output()
+        .map_err(|e| format!("Can't run {}: {}", path, e))?;
+    if !output.status.success() {
+        return Err(format!("{} exited with {}", path, output.status));
+    }
+    let line = String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or("ffmpeg").to_owned();
+    let legacy = matches!(parse_version(&line), Some(version) if version < (5, 1));
+    LEGACY_VSYNC.store(legacy, Ordering::Relaxed);
+    NO_DRAWTEXT.store(!has_filter(&path, "drawtext"), Ordering::Relaxed);
+    Ok(line)
+}
+
+/// Lines of ffmpeg output kept for error reports.
+const LOG_LINES: usize = 20;
+
+#[derive(Default)]
+struct JobState {
+    /// Fraction done, as `f32` bits.
+    progress: AtomicU32,
+    /// Whether the job has reported any progress, loading with no known length never does.
+    measured: AtomicBool,
+    cancelled: AtomicBool,
+    log: Mutex<Vec<String>>,
+}
+
+/// Progress, log and cancellation of an ffmpeg job, shared with the thread running it.
+#[derive(Clone, Default)]
+pub struct Job {
+    state: Arc<JobState>,
+}
+
+impl Job {
+    /// Fraction done, `None` while it can't be told.
+    pub fn progress(&self) -> Option<f32> {
+        match self.state.measured.load(Ordering::Relaxed) {
+            true => Some(f32::from_bits(self.state.progress.load(Ordering::Relaxed))),
+            false => None,
+        }
+    }
+
+    fn set_progress(&self, progress: f32) {
+        self.state.progress.store(progress.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        self.state.measured.store(true, Ordering::Relaxed);
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn log(&self, line: String) {
+        if let Ok(mut log) = self.state.log.lock() {
+            log.push(line);
+            let excess = log.len().saturating_sub(LOG_LINES);
+            log.drain(..excess);
+        }
+    }
+
+    /// The last lines ffmpeg printed.
+    pub fn log_excerpt(&self) -> String {
+        match self.state.log.lock() {
+            Ok(log) => log.join("\n"),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// Records progress and log lines from `event`, given the length of the input.
+    fn track(&self, event: &FfmpegEvent, duration: Option<f32>) {
+        match event {
+            FfmpegEvent::Progress(progress) => {
+                if let (Some(time), Some(duration)) = (parse_time(&progress.time), duration) {
+                    self.set_progress(time / duration.max(f32::EPSILON));
+                }
+            }
+            FfmpegEvent::Log(LogLevel::Warning | LogLevel::Error | LogLevel::Fatal, msg) => self.log(msg.clone()),
+            FfmpegEvent::Error(e) => self.log(e.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// Seconds in an ffmpeg time like `00:03:29.04`.
+fn parse_time(time: &str) -> Option<f32> {
+    time.split(':').try_fold(0.0, |total, part| Some(total * 60.0 + part.parse::<f32>().ok()?))
+}
+
+pub const CANCELLED: &str = "Cancelled";
+
+#[derive(Debug, Clone, Copy)]
+pub struct VideoInfo {
+    pub duration: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Reads the duration and size of a video by decoding a single frame.
+pub fn probe(source: &Source, job: &Job) -> Result<VideoInfo, String> {
+    let mut duration = source.duration();
+    let mut size = None;
+    let mut errors = vec![];
+    let mut child = source
+        .input(&mut ffmpeg())
+        .args(["-frames:v", "1", "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    for event in child.iter().map_err(|e| e.to_string())? {
+        job.track(&event, None);
+        if job.cancelled() {
+            child.kill().ok();
+            return Err(CANCELLED.to_owned());
+        }
+        match event {
+            FfmpegEvent::ParsedDuration(d) if !source.is_images() => {
+                duration = Some(d.duration as f32);
+            }
+            FfmpegEvent::ParsedInputStream(stream) if stream.stream_type == "Video" && size.is_none() => {
+                size = Some((stream.width, stream.height));
+            }
+            FfmpegEvent::Error(e) => errors.push(e),
+            _ => {}
+        }
+    }
+    match (duration, size) {
+        (Some(duration), Some((width, height))) => Ok(VideoInfo { duration, width, height }),
+        _ if !errors.is_empty() => Err(errors.join("\n")),
+        _ => Err("Could not read a video stream".to_owned()),
+    }
+}
+
+/// How decoded frames are spaced in time.
+#[derive(Debug, Clone, Copy, Savefile, PartialEq)]
+pub enum Sampling {
+    /// Resampled to a constant frame rate.
+    Fixed(f32),
+    /// Every source frame, at its own timestamp.
+    Native,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling::Fixed(FPS)
+    }
+}
+
+/// Presentation time of every decoded frame, in seconds from the start of the video.
+#[derive(Debug, Clone)]
+pub enum Timeline {
+    Fixed {
+        fps: f32,
+        frames: usize,
+    },
+    Native(Vec<f32>),
+}
+
+impl Timeline {
+    pub fn len(&self) -> usize {
+        match self {
+            Timeline::Fixed { frames, .. } => *frames,
+            Timeline::Native(pts) => pts.len(),
+        }
+    }
+
+    pub fn time(&self, frame: usize) -> f32 {
+        match self {
+            Timeline::Fixed { fps, .. } => (frame as f32) / fps,
+            Timeline::Native(pts) =>
+                match pts.get(frame) {
+                    Some(t) => *t,
+                    None => pts.last().cloned().unwrap_or(0.0),
+                }
+        }
+    }
+
+    /// The frame shown at `time`, the nearest one for native timestamps.
+    pub fn frame(&self, time: f32) -> usize {
+        let last = self.len().max(1) - 1;
+        match self {
+            Timeline::Fixed { fps, .. } => ((time.max(0.0) * fps).round() as usize).min(last),
+            Timeline::Native(pts) => {
+                let i = pts.partition_point(|t| *t < time);
+                match i {
+                    0 => 0,
+                    _ if i > last => last,
+                    _ if time - pts[i - 1] < pts[i] - time => i - 1,
+                    _ => i,
+                }
+            }
+        }
+    }
+
+    /// Average frame rate.
+    pub fn fps(&self) -> f32 {
+        match self {
+            Timeline::Fixed { fps, .. } => *fps,
+            Timeline::Native(pts) if pts.len() > 1 =>
+                ((pts.len() - 1) as f32) / (pts[pts.len() - 1] - pts[0]).max(f32::EPSILON),
+            Timeline::Native(_) => FPS,
+        }
+    }
+}
+
+/// Builds the timeline for `sampling`, reading every frame's timestamp in native mode.
+pub fn timeline(source: &Source, info: &VideoInfo, sampling: Sampling, job: &Job) -> Result<Timeline, String> {
+    let fps = match (sampling, source) {
+        (Sampling::Fixed(fps), _) => fps,
+        // images are evenly spaced, no need to read them all
+        (Sampling::Native, Source::Images { fps, frames, .. }) => {
+            return Ok(Timeline::Fixed { fps: *fps, frames: *frames });
+        }
+        (Sampling::Native, Source::File(_)) => {
+            return probe_timestamps(source, info.duration, job).map(Timeline::Native);
+        }
+    };
+    Ok(Timeline::Fixed {
+        fps,
+        frames: ((info.duration * fps).ceil() as usize).max(1),
+    })
+}
+
+/// Presentation timestamps of every frame in the first video stream, from `showinfo`.
+fn probe_timestamps(source: &Source, duration: f32, job: &Job) -> Result<Vec<f32>, String> {
+    let mut pts = vec![];
+    let mut errors = vec![];
+    let mut child = source
+        .input(&mut ffmpeg())
+        .args(["-map", "0:v:0", "-vf", "showinfo", "-f", "null", "-"])
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    for event in child.iter().map_err(|e| e.to_string())? {
+        job.track(&event, Some(duration));
+        if job.cancelled() {
+            child.kill().ok();
+            return Err(CANCELLED.to_owned());
+        }
+        match event {
+            FfmpegEvent::Log(_, msg) => {
+                let time = msg
+                    .split("pts_time:")
+                    .nth(1)
+                    .and_then(|rest| rest.split_whitespace().next())
+                    .and_then(|t| t.parse::<f32>().ok());
+                if let Some(time) = time {
+                    pts.push(time);
+                    job.set_progress(time / duration.max(f32::EPSILON));
+                }
+            }
+            FfmpegEvent::Error(e) => errors.push(e),
+            _ => {}
+        }
+    }
+    if pts.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    // timestamps are relative to the first frame, like the timeline of a resampled video
+    let first = pts[0];
+    Ok(pts.into_iter().map(|t| t - first).collect())
+}
+
+pub struct DecodedFrame {
+    pub index: usize,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodeSettings {
+    pub source: Source,
+    pub timestamps: bool,
+    pub interpolate: bool,
+    /// Long edge of decoded frames, `None` keeps the source size.
+    pub long_edge: Option<u32>,
+    pub sampling: Sampling,
+}
+
+impl DecodeSettings {
+    fn filter(&self, start: f32) -> String {
+        let mut filters = vec![];
+        if let Some(edge) = self.long_edge {
+            filters.push(format!("scale=w='if(gte(iw,ih),{0},-1)':h='if(lt(iw,ih),{0},-1)'", edge));
+        }
+        match self.sampling {
+            Sampling::Fixed(fps) if self.interpolate => filters.push(format!("minterpolate=fps={}:mi_mode=mci", fps)),
+            Sampling::Fixed(fps) => filters.push(format!("fps=fps={}", fps)),
+            Sampling::Native => {}
+        }
+        if self.timestamps && has_drawtext() {
+            filters.push(
+                format!(
+                    "drawtext=fontsize=50:fontcolor=GreenYellow:text='%{{pts\\:flt\\:{}}}':x=(w-text_w):y=(h-text_h)",
+                    start
+                )
+            );
+        }
+        match filters.is_empty() {
+            true => "null".to_owned(),
+            false => filters.join(","),
+        }
+    }
+}
+
+struct DecodeRequest {
+    id: u64,
+    start: usize,
+    count: usize,
+}
+
+enum DecoderEvent {
+    Frame(DecodedFrame),
+    /// Request `id` has been decoded as far as it goes, up to the frame before `end`.
+    Done {
+        id: u64,
+        end: usize,
+    },
+    Error(String),
+}
+
+/// Decodes frames on a worker thread. A new request replaces the one being decoded,
+/// so seeking never waits for frames nobody is looking at anymore.
+pub struct Decoder {
+    requests: mpsc::Sender<DecodeRequest>,
+    events: mpsc::Receiver<DecoderEvent>,
+    _thread: JoinHandle<()>,
+    /// Frames of the last request that have not arrived yet.
+    pending: Option<(usize, usize)>,
+    /// Id of the last request, so a `Done` from one it replaced is ignored.
+    last_request: u64,
+    /// First frame ffmpeg ran out before, as the timeline can count a frame past the
+    /// end of the stream. Asking for it again would only start ffmpeg for nothing.
+    end: Option<usize>,
+    /// Errors from ffmpeg since the last `take_errors`.
+    errors: Vec<String>,
+}
+
+impl Decoder {
+    pub fn new(settings: DecodeSettings, timeline: Timeline) -> Decoder {
+        let (requests, request_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel();
+        Decoder {
+            requests,
+            events,
+            _thread: std::thread::spawn(move || Self::run(settings, timeline, request_rx, event_tx)),
+            pending: None,
+            last_request: 0,
+            end: None,
+            errors: vec![],
+        }
+    }
+
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn request(&mut self, start: usize, count: usize) {
+        self.last_request += 1;
+        self.pending = Some((start, start + count));
+        self.requests.send(DecodeRequest { id: self.last_request, start, count }).ok();
+    }
+
+    /// Whether `index` is part of the request currently being decoded.
+    pub fn is_pending(&self, index: usize) -> bool {
+        match self.pending {
+            Some((start, end)) => index >= start && index < end,
+            None => false,
+        }
+    }
+
+    /// Whether `index` is past the last frame ffmpeg could decode.
+    pub fn is_past_end(&self, index: usize) -> bool {
+        match self.end {
+            Some(end) => index >= end,
+            None => false,
+        }
+    }
+
+    pub fn receive(&mut self) -> Vec<DecodedFrame> {
+        let mut frames = vec![];
+        for event in self.events.try_iter() {
+            match event {
+                DecoderEvent::Frame(frame) => frames.push(frame),
+                DecoderEvent::Done { id, end } if id == self.last_request => {
+                    if let Some((_, requested)) = self.pending.take() {
+                        if end < requested {
+                            self.end = Some(self.end.map_or(end, |e| e.min(end)));
+                        }
+                    }
+                }
+                DecoderEvent::Done { .. } => {}
+                DecoderEvent::Error(e) => self.errors.push(e),
+            }
+        }
+        frames
+    }
+
+    fn run(
+        settings: DecodeSettings,
+        timeline: Timeline,
+        requests: mpsc::Receiver<DecodeRequest>,
+        events: mpsc::Sender<DecoderEvent>
+    ) {
+        let mut next = requests.recv().ok();
+        while let Some(request) = next.take() {
+            let start = timeline.time(request.start);
+            // seek just before the frame, so rounding never skips it
+            let seek = match settings.sampling {
+                Sampling::Fixed(_) => start,
+                Sampling::Native => (start - 0.5 / timeline.fps()).max(0.0),
+            };
+            let mut child = match
+                settings.source
+                    .input(ffmpeg().args(["-ss", &seek.to_string()]))
+                    .args(["-vf", &settings.filter(start)])
+                    .args(passthrough())
+                    .args(["-frames:v", &request.count.to_string()])
+                    .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+                    .spawn()
+            {
+                Ok(child) => child,
+                Err(e) => {
+                    events.send(DecoderEvent::Error(e.to_string())).ok();
+                    events.send(DecoderEvent::Done { id: request.id, end: request.start }).ok();
+                    next = requests.recv().ok();
+                    continue;
+                }
+            };
+            let ffmpeg_events = match child.iter() {
+                Ok(events) => events,
+                Err(e) => {
+                    events.send(DecoderEvent::Error(e.to_string())).ok();
+                    events.send(DecoderEvent::Done { id: request.id, end: request.start }).ok();
+                    next = requests.recv().ok();
+                    continue;
+                }
+            };
+            let mut end = request.start;
+            for event in ffmpeg_events {
+                match event {
+                    FfmpegEvent::OutputFrame(frame) => {
+                        end = end.max(request.start + (frame.frame_num as usize) + 1);
+                        let decoded = DecodedFrame {
+                            index: request.start + (frame.frame_num as usize),
+                            width: frame.width,
+                            height: frame.height,
+                            data: frame.data,
+                        };
+                        if events.send(DecoderEvent::Frame(decoded)).is_err() {
+                            break;
+                        }
+                    }
+                    FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, msg) | FfmpegEvent::Error(msg) => {
+                        events.send(DecoderEvent::Error(msg)).ok();
+                    }
+                    _ => {}
+                }
+                match requests.try_recv() {
+                    Ok(newer) => {
+                        // skip straight to the most recent request
+                        next = Some(requests.try_iter().last().unwrap_or(newer));
+                        break;
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => {}
+                }
+            }
+            child.kill().ok();
+            child.wait().ok();
+            if next.is_none() {
+                events.send(DecoderEvent::Done { id: request.id, end }).ok();
+                next = requests.recv().ok();
+            }
+        }
+    }
+}
+
+/// Decoded frames kept in RAM, evicting the ones furthest from the playhead once over `budget` bytes.
+pub struct FrameCache {
+    frames: HashMap<usize, DecodedFrame>,
+    pub budget: usize,
+    used: usize,
+}
+
+impl FrameCache {
+    pub fn new(budget: usize) -> FrameCache {
+        FrameCache {
+            frames: HashMap::new(),
+            budget,
+            used: 0,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&DecodedFrame> {
+        self.frames.get(&index)
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.frames.contains_key(&index)
+    }
+
+    /// The cached frame closest to `index`.
+    pub fn nearest(&self, index: usize) -> Option<&DecodedFrame> {
+        self.frames
+            .values()
+            .min_by_key(|f| (f.index as i64 - index as i64).abs())
+    }
+
+    pub fn insert(&mut self, frame: DecodedFrame, playhead: usize) {
+        self.used += frame.data.len();
+        if let Some(old) = self.frames.insert(frame.index, frame) {
+            self.used -= old.data.len();
+        }
+        self.evict(playhead);
+    }
+
+    pub fn evict(&mut self, playhead: usize) {
+        while self.used > self.budget && self.frames.len() > 1 {
+            let furthest = *self.frames
+                .keys()
+                .max_by_key(|i| (**i as i64 - playhead as i64).abs())
+                .unwrap();
+            if let Some(frame) = self.frames.remove(&furthest) {
+                self.used -= frame.data.len();
+            }
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions() {
+        assert_eq!(parse_version("ffmpeg version 4.4.2-0ubuntu0.22.04.1 Copyright (c) 2000-2021"), Some((4, 4)));
+        assert_eq!(parse_version("ffmpeg version n6.0 Copyright (c) 2000-2023"), Some((6, 0)));
+        assert_eq!(parse_version("ffmpeg version 5.1.3-1 Copyright"), Some((5, 1)));
+        assert_eq!(parse_version("ffmpeg version 7 Copyright"), Some((7, 0)));
+        assert_eq!(parse_version("ffmpeg version N-111800-g2cf4e4a Copyright"), None);
+        assert_eq!(parse_version("ffmpeg version 2023-06-11-git-09621fd7d9-full_build-www.gyan.dev"), None);
+        assert_eq!(parse_version("ffmpeg"), None);
+    }
+
+    #[test]
+    fn timeline_frames() {
+        let fixed = Timeline::Fixed { fps: 10.0, frames: 5 };
+        assert_eq!(fixed.frame(0.26), 3);
+        assert_eq!(fixed.frame(9.0), 4);
+        let native = Timeline::Native(vec![0.0, 0.1, 0.25, 0.3]);
+        assert_eq!(native.frame(0.2), 2);
+        assert_eq!(native.frame(0.12), 1);
+        assert_eq!(native.time(2), 0.25);
+        assert_eq!(native.frame(5.0), 3);
+    }
+
+    #[test]
+    fn short_requests_mark_the_end() {
+        let (requests, _request_rx) = mpsc::channel();
+        let (events, event_rx) = mpsc::channel();
+        let mut decoder = Decoder {
+            requests,
+            events: event_rx,
+            _thread: std::thread::spawn(|| {}),
+            pending: None,
+            last_request: 0,
+            end: None,
+            errors: vec![],
+        };
+        decoder.request(90, 10);
+        // a request that was replaced says nothing about the end
+        events.send(DecoderEvent::Done { id: 0, end: 90 }).unwrap();
+        decoder.receive();
+        assert!(decoder.is_pending(95));
+        assert!(!decoder.is_past_end(99));
+
+        events.send(DecoderEvent::Done { id: 1, end: 99 }).unwrap();
+        decoder.receive();
+        assert!(!decoder.is_pending(95));
+        assert!(!decoder.is_past_end(98));
+        assert!(decoder.is_past_end(99));
+
+        // a full request leaves the end alone
+        decoder.request(0, 10);
+        events.send(DecoderEvent::Done { id: 2, end: 10 }).unwrap();
+        decoder.receive();
+        assert!(decoder.is_past_end(99));
+    }
+}
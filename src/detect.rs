@@ -0,0 +1,231 @@
+use ffmpeg_sidecar::event::FfmpegEvent;
+
+use crate::decoder::{ ffmpeg, Timeline };
+use crate::sequence::Source;
+
+/// Flights shorter than this are bed wobble, not bounces.
+pub const MIN_FLIGHT: f32 = 0.3;
+
+/// A take-off or landing found by analysis, waiting for the user to accept it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Suggestion {
+    pub time: f32,
+    /// 0 to 1, how sharply the motion or sound changed at this point.
+    pub confidence: f32,
+}
+
+/// Mean absolute difference between consecutive frames inside `roi`, given as
+/// `[left, top, right, bottom]` fractions of the frame, sampled at `fps`.
+pub fn motion_profile(source: &Source, roi: [f32; 4], fps: f32) -> Result<Vec<f32>, String> {
+    let filter = format!(
+        "crop=w=iw*{}:h=ih*{}:x=iw*{}:y=ih*{},scale=64:64,fps=fps={},format=gray",
+        roi[2] - roi[0],
+        roi[3] - roi[1],
+        roi[0],
+        roi[1],
+        fps
+    );
+    let mut motion = vec![];
+    let mut previous: Option<Vec<u8>> = None;
+    let mut errors = vec![];
+    source
+        .input(&mut ffmpeg())
+        .args(["-vf", &filter])
+        .args(["-f", "rawvideo", "-pix_fmt", "gray", "-"])
+        .spawn()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map_err(|e| e.to_string())?
+        .for_each(|event| {
+            match event {
+                FfmpegEvent::OutputFrame(frame) => {
+                    motion.push(match &previous {
+                        Some(p) =>
+                            p
+                                .iter()
+                                .zip(frame.data.iter())
+                                .map(|(a, b)| (*a as f32 - *b as f32).abs())
+                                .sum::<f32>() / (frame.data.len() as f32),
+                        None => 0.0,
+                    });
+                    previous = Some(frame.data);
+                }
+                FfmpegEvent::Error(e) => errors.push(e),
+                _ => {}
+            }
+        });
+    if motion.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    Ok(motion)
+}
+
+pub fn percentile(values: &[f32], p: f32) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted[(((sorted.len() - 1) as f32) * p) as usize]
+}
+
+/// Finds bed contacts in a motion profile sampled at `fps`. The bed only moves while the
+/// athlete is on it, so every stretch of high motion is a contact and the gaps are flights.
+/// Returns alternating take-offs and landings, like `Video.points`.
+pub fn detect_contacts(motion: &[f32], fps: f32) -> Vec<Suggestion> {
+    if motion.len() < 3 {
+        return vec![];
+    }
+    let smooth = (0..motion.len())
+        .map(|i| {
+            let window = &motion[i.saturating_sub(1)..(i + 2).min(motion.len())];
+            window.iter().sum::<f32>() / (window.len() as f32)
+        })
+        .collect::<Vec<f32>>();
+    let low = percentile(&smooth, 0.2);
+    let high = percentile(&smooth, 0.95);
+    if (high - low).abs() < f32::EPSILON {
+        return vec![];
+    }
+    let threshold = (low + high) / 2.0;
+    let confidence = |i: usize| {
+        let before = smooth[i.saturating_sub(2)];
+        let after = smooth[(i + 2).min(smooth.len() - 1)];
+        ((after - before).abs() / (high - low)).clamp(0.0, 1.0)
+    };
+
+    let mut suggestions: Vec<Suggestion> = vec![];
+    let mut take_off: Option<usize> = None;
+    for i in 1..smooth.len() {
+        let was_contact = smooth[i - 1] >= threshold;
+        let is_contact = smooth[i] >= threshold;
+        if was_contact && !is_contact {
+            take_off = Some(i);
+        }
+        if !was_contact && is_contact {
+            if let Some(t) = take_off.take() {
+                if ((i - t) as f32) / fps >= MIN_FLIGHT {
+                    suggestions.push(Suggestion {
+                        time: (t as f32) / fps,
+                        confidence: confidence(t),
+                    });
+                    suggestions.push(Suggestion {
+                        time: (i as f32) / fps,
+                        confidence: confidence(i),
+                    });
+                }
+            }
+        }
+    }
+    suggestions
+}
+
+/// Moves `suggestions` onto the nearest frame of `timeline`, so accepted points land on frames.
+pub fn snap_to_frames(suggestions: Vec<Suggestion>, timeline: &Timeline) -> Vec<Suggestion> {
+    suggestions
+        .into_iter()
+        .map(|s| Suggestion {
+            time: timeline.time(timeline.frame(s.time)),
+            ..s
+        })
+        .collect()
+}
+
+/// Whether a flight from `take_off` to `landing` can be added to `points` without
+/// landing inside a marked flight, which would swap take-offs and landings after it.
+pub fn fits_flight(points: &[f32], take_off: f32, landing: f32) -> bool {
+    points.iter().filter(|p| **p < take_off).count() % 2 == 0 &&
+        !points.iter().any(|p| *p >= take_off && *p <= landing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{ ffmpeg_path, FPS };
+
+    /// Bed motion that is high for `true` stretches and still for `false` ones, in seconds.
+    fn profile(stretches: &[(bool, f32)], fps: f32) -> Vec<f32> {
+        stretches
+            .iter()
+            .flat_map(|(contact, seconds)| {
+                let value = match contact {
+                    true => 10.0,
+                    false => 0.0,
+                };
+                (0..(seconds * fps).round() as usize).map(move |_| value)
+            })
+            .collect()
+    }
+
+    fn times(suggestions: &[Suggestion]) -> Vec<f32> {
+        suggestions.iter().map(|s| s.time).collect()
+    }
+
+    fn assert_near(found: &[f32], expected: &[f32], tolerance: f32) {
+        assert_eq!(found.len(), expected.len(), "{:?}", found);
+        for (f, e) in found.iter().zip(expected) {
+            assert!((f - e).abs() <= tolerance, "{:?} vs {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn finds_take_offs_and_landings() {
+        let motion = profile(&[(true, 0.5), (false, 1.0), (true, 0.5), (false, 1.2), (true, 0.5)], FPS);
+        let suggestions = detect_contacts(&motion, FPS);
+        assert_near(&times(&suggestions), &[0.5, 1.5, 2.0, 3.2], 1.0 / FPS);
+        assert!(suggestions.iter().all(|s| s.confidence > 0.5));
+    }
+
+    #[test]
+    fn rejects_short_flights() {
+        let motion = profile(&[(true, 0.5), (false, MIN_FLIGHT * 0.5), (true, 0.5), (false, 1.0), (true, 0.5)], FPS);
+        let suggestions = detect_contacts(&motion, FPS);
+        assert_near(&times(&suggestions), &[1.0 + MIN_FLIGHT * 0.5, 2.0 + MIN_FLIGHT * 0.5], 1.0 / FPS);
+    }
+
+    #[test]
+    fn nothing_in_still_video() {
+        assert!(detect_contacts(&[1.0; 100], FPS).is_empty());
+        assert!(detect_contacts(&[1.0, 5.0], FPS).is_empty());
+    }
+
+    #[test]
+    fn finds_flights_in_footage() {
+        if std::process::Command::new(ffmpeg_path()).arg("-version").output().is_err() {
+            eprintln!("ffmpeg not found, skipping");
+            return;
+        }
+        // noise where the bed moves, a still grey frame while the athlete is in the air
+        let contact = "color=c=gray:s=160x120:r=48:d=0.5,noise=alls=80:allf=t";
+        let flight = |seconds: f32| format!("color=c=gray:s=160x120:r=48:d={}", seconds);
+        let path = std::env::temp_dir().join(format!("detect-test-{}.mkv", std::process::id()));
+        let status = std::process::Command
+            ::new(ffmpeg_path())
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "lavfi", "-i", contact, "-f", "lavfi", "-i", &flight(1.0)])
+            .args(["-f", "lavfi", "-i", contact, "-f", "lavfi", "-i", &flight(1.2)])
+            .args(["-f", "lavfi", "-i", contact])
+            .args(["-filter_complex", "[0][1][2][3][4]concat=n=5:v=1:a=0", "-c:v", "ffv1"])
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let source = Source::File(path.display().to_string());
+        let motion = motion_profile(&source, [0.1, 0.1, 0.9, 0.9], FPS);
+        std::fs::remove_file(&path).ok();
+        let suggestions = detect_contacts(&motion.unwrap(), FPS);
+        assert_near(&times(&suggestions), &[0.5, 1.5, 2.0, 3.2], 2.0 / FPS);
+    }
+
+    #[test]
+    fn flights_fit_between_marked_ones() {
+        let points = [1.0, 2.0, 3.0, 4.0];
+        assert!(fits_flight(&points, 2.2, 2.8));
+        assert!(fits_flight(&points, 4.5, 5.5));
+        assert!(fits_flight(&[], 0.5, 1.5));
+        // inside a flight
+        assert!(!fits_flight(&points, 1.2, 1.8));
+        // around a landing
+        assert!(!fits_flight(&points, 1.5, 2.5));
+        // around a whole flight
+        assert!(!fits_flight(&points, 0.5, 2.5));
+    }
+}
@@ -0,0 +1,284 @@
+use std::{ fs, io, path::Path };
+use macroquad::logging::error;
+use nfd2::Response;
+use rust_xlsxwriter::{ Format, Workbook, XlsxError };
+
+use crate::Judged;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+pub enum Cell {
+    Text(String),
+    Number(f32),
+    Empty,
+}
+
+pub const HEADER: [&str; 17] = [
+    "Date",
+    "Athlete",
+    "Routine",
+    "Element",
+    "Skill",
+    "Notation",
+    "DD",
+    "E",
+    "E J1",
+    "E J2",
+    "E J3",
+    "E J4",
+    "E J5",
+    "HD",
+    "ToF",
+    "Penalty",
+    "Total",
+];
+
+/// One row per element followed by a `Total` row for every judged routine.
+/// The routine of each record has to be loaded for the skill and DD columns.
+pub fn rows(judged: &[Judged]) -> Vec<Vec<Cell>> {
+    let mut rows = vec![];
+    for j in judged {
+        let routine_name = match &j.routine {
+            Some(r) => r.name.clone(),
+            None => j.routine_id.clone(),
+        };
+        let info = || vec![
+            Cell::Text(j.date_of_creation.clone()),
+            Cell::Text(j.athlete.clone()),
+            Cell::Text(routine_name.clone())
+        ];
+
+        for i in 0..10 {
+            let mut row = info();
+            row.push(Cell::Number((i + 1) as f32));
+            match &j.routine {
+                Some(r) => {
+                    row.push(Cell::Text(r.skills[i].name()));
+                    row.push(Cell::Text(r.skills[i].notation()));
+                    row.push(Cell::Number(r.skills[i].diff()));
+                }
+                None => {
+                    row.push(Cell::Empty);
+                    row.push(Cell::Empty);
+                    row.push(Cell::Empty);
+                }
+            }
+            if j.five_judges {
+                row.push(Cell::Empty);
+                for k in 0..5 {
+                    row.push(Cell::Number(j.execution_5[k][i]));
+                }
+            } else {
+                row.push(Cell::Number(j.execution_1[i]));
+                for _ in 0..5 {
+                    row.push(Cell::Empty);
+                }
+            }
+            row.push(Cell::Number(j.hd[i]));
+            row.push(Cell::Number(j.tof[i]));
+            row.push(Cell::Empty);
+            row.push(Cell::Empty);
+            rows.push(row);
+        }
+
+        let mut row = info();
+        row.push(Cell::Text("Total".to_owned()));
+        row.push(Cell::Empty);
+        row.push(Cell::Empty);
+        row.push(Cell::Number(j.difficulty()));
+        row.push(Cell::Number(j.execution_deduction()));
+        for total in j.judge_totals() {
+            row.push(match j.five_judges {
+                true => Cell::Number(total),
+                false => Cell::Empty,
+            });
+        }
+        row.push(Cell::Number(j.hd_total()));
+        row.push(Cell::Number(j.tof_total));
+        row.push(Cell::Number(j.penalty));
+        row.push(Cell::Number(j.total()));
+        rows.push(row);
+    }
+    rows
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace("\"", "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+pub fn to_csv(judged: &[Judged]) -> String {
+    let mut csv = HEADER.join(",") + "\n";
+    for row in rows(judged) {
+        csv += &row
+            .iter()
+            .map(|cell| match cell {
+                Cell::Text(t) => csv_field(t),
+                Cell::Number(n) => format!("{:.2}", n),
+                Cell::Empty => "".to_owned(),
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        csv += "\n";
+    }
+    csv
+}
+
+pub fn save_csv<P: AsRef<Path>>(path: P, judged: &[Judged]) -> io::Result<()> {
+    fs::write(path, to_csv(judged))
+}
+
+pub fn save_xlsx<P: AsRef<Path>>(path: P, judged: &[Judged]) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Results")?;
+    for (col, title) in HEADER.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, &bold)?;
+    }
+    for (row, cells) in rows(judged).iter().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            match cell {
+                Cell::Text(t) => {
+                    sheet.write_string((row + 1) as u32, col as u16, t)?;
+                }
+                Cell::Number(n) => {
+                    // f32 -> f64 would otherwise show 0.1 as 0.100000001490116
+                    let n = ((*n as f64) * 100.0).round() / 100.0;
+                    sheet.write_number((row + 1) as u32, col as u16, n)?;
+                }
+                Cell::Empty => {}
+            }
+        }
+    }
+    workbook.save(path)
+}
+
+/// Asks where to save and writes the judged routines in the chosen format.
+pub fn export_dialog(judged: &[Judged], format: ExportFormat) {
+    let path = match nfd2::open_save_dialog(Some(format.extension()), None) {
+        Ok(Response::Okay(path)) => path,
+        _ => {
+            return;
+        }
+    };
+    let path = match path.extension() {
+        Some(_) => path,
+        None => path.with_extension(format.extension()),
+    };
+    let result = match format {
+        ExportFormat::Csv => save_csv(&path, judged).map_err(|e| e.to_string()),
+        ExportFormat::Xlsx => save_xlsx(&path, judged).map_err(|e| e.to_string()),
+    };
+    match result {
+        Ok(_) => {}
+        Err(e) => {
+            error!("Error exporting results: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ BodyPart, Routine, Skill };
+
+    fn judged() -> Judged {
+        let mut routine = Routine::blank();
+        routine.name = "Set \"A\"\nfinal".to_owned();
+        routine.skills[0] = Skill::from_notation("40 o".to_owned(), BodyPart::Feet).unwrap();
+        let mut j = Judged::new();
+        j.routine = Some(routine);
+        j.athlete = "Smith, Jo".to_owned();
+        j.date_of_creation = "2024-05-01 10:00".to_owned();
+        j
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn to_csv_escapes_names() {
+        let csv = to_csv(&[judged()]);
+        assert!(csv.starts_with(&(HEADER.join(",") + "\n")));
+        let info = "2024-05-01 10:00,\"Smith, Jo\",\"Set \"\"A\"\"\nfinal\",";
+        // ten elements and the total
+        assert_eq!(csv.matches(info).count(), 11);
+        let skill = Skill::from_notation("40 o".to_owned(), BodyPart::Feet).unwrap();
+        assert!(csv.contains(&format!("{}1.00,{},40 o,0.50,", info, csv_field(&skill.name()))));
+        assert!(csv.contains(&format!("{}Total,,,0.50,", info)));
+    }
+
+    #[test]
+    fn to_csv_columns() {
+        // without a routine loaded no field has a comma in it
+        let mut j = Judged::new();
+        j.routine_id = "routine".to_owned();
+        let csv = to_csv(&[j]);
+        for line in csv.lines() {
+            assert_eq!(line.split(',').count(), HEADER.len());
+        }
+        assert_eq!(csv.lines().count(), 12);
+    }
+
+    #[test]
+    fn execution_score_one_judge() {
+        let mut j = judged();
+        j.execution_1 = [0.1; 10];
+        assert!(close(j.execution_deduction(), 1.0));
+        assert!(close(j.execution_score(), 9.0));
+    }
+
+    #[test]
+    fn execution_score_five_judges_drops_highest_and_lowest() {
+        let mut j = judged();
+        j.five_judges = true;
+        for k in 0..5 {
+            for i in 0..=k {
+                j.execution_5[k][i] = 0.2;
+            }
+        }
+        // totals 0.2 to 1.0, the middle three are 0.4 + 0.6 + 0.8
+        assert!(close(j.execution_deduction(), 1.8));
+        assert!(close(j.execution_score(), 28.2));
+        assert_eq!(j.judge_totals().map(|t| (t * 10.0).round()), [2.0, 4.0, 6.0, 8.0, 10.0]);
+    }
+
+    #[test]
+    fn total_adds_every_part() {
+        let mut j = judged();
+        j.execution_1 = [0.1; 10];
+        j.hd[0] = 0.3;
+        j.tof_total = 15.0;
+        j.penalty = 0.3;
+        assert!(close(j.difficulty(), 0.5));
+        // 9.0 execution + 0.5 difficulty + 9.7 HD + 15.0 ToF - 0.3 penalty
+        assert!(close(j.total(), 33.9));
+    }
+}
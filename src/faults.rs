@@ -0,0 +1,84 @@
+use strum_macros::EnumIter;
+
+#[derive(Debug, Clone, Copy, Savefile, PartialEq, Eq, EnumIter)]
+pub enum FaultCategory {
+    BentLegs,
+    LegsApart,
+    FlexedFeet,
+    ArmThrow,
+    OpenShape,
+    LateOpening,
+    Travel,
+    Other,
+}
+
+impl FaultCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FaultCategory::BentLegs => "Bent legs",
+            FaultCategory::LegsApart => "Legs apart",
+            FaultCategory::FlexedFeet => "Flexed feet",
+            FaultCategory::ArmThrow => "Arm throw",
+            FaultCategory::OpenShape => "Open shape",
+            FaultCategory::LateOpening => "Late opening",
+            FaultCategory::Travel => "Travel",
+            FaultCategory::Other => "Other",
+        }
+    }
+}
+
+/// An execution fault seen at `time` seconds into the video.
+#[derive(Debug, Clone, Savefile, PartialEq)]
+pub struct Fault {
+    pub time: f32,
+    pub category: FaultCategory,
+    pub deduction: f32,
+    pub note: String,
+}
+
+/// Index of the element whose flight, between a take-off and landing in `points`, contains `time`.
+pub fn element_at(points: &[f32], time: f32) -> Option<usize> {
+    points.chunks_exact(2).position(|pair| time >= pair[0] && time <= pair[1])
+}
+
+/// Execution marks from the faults of each element, capped at 0.5 like a judge's mark.
+pub fn fault_marks(faults: &[Fault], points: &[f32]) -> [f32; 10] {
+    let mut marks = [0.0; 10];
+    for fault in faults {
+        if let Some(i) = element_at(points, fault.time).filter(|i| *i < 10) {
+            marks[i] += fault.deduction;
+        }
+    }
+    // marks are whole tenths, which the execution panel compares exactly
+    marks.map(|m| ((m * 10.0).round() / 10.0).min(0.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fault(time: f32, deduction: f32) -> Fault {
+        Fault { time, category: FaultCategory::Other, deduction, note: String::new() }
+    }
+
+    #[test]
+    fn faults_land_on_their_flight() {
+        let points = [1.0, 2.0, 2.5, 3.5];
+        assert_eq!(element_at(&points, 0.5), None);
+        assert_eq!(element_at(&points, 1.0), Some(0));
+        assert_eq!(element_at(&points, 2.2), None);
+        assert_eq!(element_at(&points, 3.5), Some(1));
+        // a take-off without its landing is not a flight
+        assert_eq!(element_at(&[1.0, 2.0, 3.0], 3.1), None);
+    }
+
+    #[test]
+    fn marks_are_summed_and_capped() {
+        let points = [1.0, 2.0, 2.5, 3.5];
+        let faults = [fault(1.2, 0.1), fault(1.8, 0.2), fault(3.0, 0.3), fault(3.1, 0.4), fault(2.2, 0.5)];
+        let marks = fault_marks(&faults, &points);
+        assert_eq!(marks[0], 0.3);
+        assert_eq!(marks[1], 0.5);
+        assert!(marks[2..].iter().all(|m| *m == 0.0));
+    }
+}
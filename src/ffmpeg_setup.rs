@@ -0,0 +1,142 @@
+use std::thread::JoinHandle;
+use egui::Color32;
+use nfd2::Response;
+
+use crate::decoder::{ ffmpeg_notes, ffmpeg_version, set_ffmpeg_path };
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FfmpegStatus {
+    Checking,
+    Downloading,
+    /// Version line of the working binary.
+    Ready(String),
+    Missing(String),
+}
+
+/// Where ffmpeg is found, and whether it works. Video features stay off until it does,
+/// everything else works without it.
+pub struct FfmpegSetup {
+    /// Binary chosen by the user, empty for the one next to the app or on the PATH.
+    pub path: String,
+    pub status: FfmpegStatus,
+    task: Option<JoinHandle<FfmpegStatus>>,
+}
+
+fn check() -> FfmpegStatus {
+    match ffmpeg_version() {
+        Ok(version) => FfmpegStatus::Ready(version),
+        Err(e) => FfmpegStatus::Missing(e),
+    }
+}
+
+impl FfmpegSetup {
+    pub fn new() -> FfmpegSetup {
+        FfmpegSetup {
+            path: String::new(),
+            status: FfmpegStatus::Checking,
+            task: None,
+        }
+    }
+
+    pub fn available(&self) -> bool {
+        matches!(self.status, FfmpegStatus::Ready(_))
+    }
+
+    fn busy(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Uses `path` from now on and checks it in the background.
+    pub fn check(&mut self) {
+        if self.busy() {
+            return;
+        }
+        set_ffmpeg_path(&self.path);
+        self.status = FfmpegStatus::Checking;
+        self.task = Some(std::thread::spawn(check));
+    }
+
+    /// Downloads ffmpeg next to the app and switches to it.
+    pub fn download(&mut self) {
+        if self.busy() {
+            return;
+        }
+        self.path = String::new();
+        set_ffmpeg_path(&self.path);
+        self.status = FfmpegStatus::Downloading;
+        self.task = Some(
+            std::thread::spawn(|| {
+                match ffmpeg_sidecar::download::auto_download() {
+                    Ok(_) => check(),
+                    Err(e) => FfmpegStatus::Missing(format!("Download failed: {}", e)),
+                }
+            })
+        );
+    }
+
+    pub fn update(&mut self) {
+        if !self.task.as_ref().is_some_and(|t| t.is_finished()) {
+            return;
+        }
+        self.status = match self.task.take().unwrap().join() {
+            Ok(status) => status,
+            Err(_) => FfmpegStatus::Missing("ffmpeg check crashed".to_owned()),
+        };
+    }
+
+    pub fn display(&mut self, ui: &mut egui::Ui) {
+        match &self.status {
+            FfmpegStatus::Checking => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Checking ffmpeg");
+                });
+            }
+            FfmpegStatus::Downloading => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Downloading ffmpeg");
+                });
+            }
+            FfmpegStatus::Ready(version) => {
+                ui.label(version);
+                for note in ffmpeg_notes() {
+                    ui.colored_label(Color32::YELLOW, note);
+                }
+            }
+            FfmpegStatus::Missing(e) => {
+                ui.colored_label(Color32::RED, e);
+                ui.label("Video features are off until ffmpeg is found");
+            }
+        }
+        ui.add_enabled_ui(!self.busy(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Binary ");
+                if ui.add(egui::TextEdit::singleline(&mut self.path).hint_text("bundled or PATH")).lost_focus() {
+                    self.check();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.button(format!("{} Browse", egui_phosphor::FOLDER_OPEN)).clicked().then(|| {
+                    if let Ok(Response::Okay(path)) = nfd2::open_file_dialog(None, None) {
+                        self.path = path.display().to_string();
+                        self.check();
+                    }
+                });
+                ui.button("Default").clicked().then(|| {
+                    self.path = String::new();
+                    self.check();
+                });
+                ui.button(format!("{} Check", egui_phosphor::ARROWS_CLOCKWISE)).clicked().then(|| {
+                    self.check();
+                });
+            });
+            ui.button(format!("{} Download ffmpeg", egui_phosphor::CLOUD_ARROW_DOWN))
+                .on_hover_text("Needs internet access, the binary is saved next to the app")
+                .clicked()
+                .then(|| {
+                    self.download();
+                });
+        });
+    }
+}
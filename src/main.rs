@@ -16,14 +16,57 @@ use strum_macros::EnumIter;
 
 use chrono;
 
-use ffmpeg_sidecar::{ self};
-
 mod skill;
 use skill::*;
 
+mod decoder;
+
+mod sequence;
+
+mod detect;
+
+mod clips;
+
+mod strobe;
+
+mod annotate;
+
+mod faults;
+use faults::*;
+
+mod watch;
+use watch::FolderWatch;
+
+mod compare;
+use compare::*;
+
+mod tracking;
+
+mod audio;
+
+mod textures;
+
+mod ffmpeg_setup;
+use ffmpeg_setup::FfmpegSetup;
+
 mod video;
 use video::*;
 
+mod export;
+use export::*;
+
+mod analytics;
+use analytics::*;
+
+mod scoreboard;
+use scoreboard::*;
+
+mod server;
+use server::*;
+
+mod signoff;
+use signoff::*;
+
 
 #[derive(PartialEq, Clone, Copy, Savefile, Debug)]
 enum Tab {
@@ -281,13 +324,48 @@ struct Data {
     theme: WindowTheme,
     judged: Vec<Judged>,
     zoom: f32,
+    /// Memory shared by all video windows for frames, in MB.
+    video_memory: usize,
+    judge_report: JudgeReport,
+    scoreboard: Scoreboard,
+    server_settings: ServerSettings,
+    server: Option<Server>,
+    server_error: String,
+    /// Saved video sessions that are not open.
+    video_sessions: Vec<VideoSession>,
+    ffmpeg: FfmpegSetup,
+    watch: FolderWatch,
 }
 
 impl Data {
+    fn new() -> Data {
+        Data {
+            zoom: 1.0,
+            video_memory: 1024,
+            routines: vec![],
+            theme: WindowTheme::Light,
+            judged: vec![],
+            judge_report: JudgeReport::new(),
+            scoreboard: Scoreboard::new(),
+            server_settings: ServerSettings::default(),
+            server: None,
+            server_error: String::new(),
+            video_sessions: vec![],
+            ffmpeg: FfmpegSetup::new(),
+            watch: FolderWatch::new(),
+        }
+    }
+
     fn render(&mut self, egui_ctx: &egui::Context) {
         for r in self.routines.iter_mut() {
             r.display(&egui_ctx);
         }
+        if self.judge_report.open {
+            self.judge_report.display(egui_ctx, &self.judged);
+        }
+        if self.scoreboard.open {
+            self.scoreboard.display(egui_ctx, &self.judged);
+        }
         for r in self.judged.iter_mut() {
             if r.open {
             if r.routine_id == "" {
@@ -322,7 +400,7 @@ impl Data {
             }
         }
         for i in self.judged.iter() {
-            match savefile::save_file(format!("Data/judge/{}.bin", i.id), 1, i) {
+            match savefile::save_file(format!("Data/judge/{}.bin", i.id), 5, i) {
                 Ok(_) => {}
                 Err(e) => {
                     error!("Error saving file: {}", e);
@@ -341,10 +419,30 @@ impl Data {
             Ok(_) => {}
             Err(e) => { error!("Error saving file: {}", e) }
         }
+        match savefile::save_file("Data/scoreboard_theme.bin", 1, &self.scoreboard.theme) {
+            Ok(_) => {}
+            Err(e) => { error!("Error saving file: {}", e) }
+        }
+        match savefile::save_file("Data/video_memory.bin", 1, &self.video_memory) {
+            Ok(_) => {}
+            Err(e) => { error!("Error saving file: {}", e) }
+        }
         match savefile::save_file("Data/zoom.bin", 1, &self.zoom) {
             Ok(_) => {}
             Err(e) => { error!("Error saving file: {}", e) }
         }
+        match savefile::save_file("Data/server.bin", 1, &self.server_settings) {
+            Ok(_) => {}
+            Err(e) => { error!("Error saving file: {}", e) }
+        }
+        match savefile::save_file("Data/ffmpeg.bin", 1, &self.ffmpeg.path) {
+            Ok(_) => {}
+            Err(e) => { error!("Error saving file: {}", e) }
+        }
+        match savefile::save_file("Data/watch.bin", 1, &self.watch.settings) {
+            Ok(_) => {}
+            Err(e) => { error!("Error saving file: {}", e) }
+        }
     }
 
     fn load_files(&mut self) {
@@ -356,6 +454,22 @@ impl Data {
                 error!("Error loading file: {}", e);
             }
         }
+        match savefile::load_file("Data/scoreboard_theme.bin", 1) {
+            Ok(theme) => {
+                self.scoreboard.theme = theme;
+            }
+            Err(e) => {
+                error!("Error loading file: {}", e);
+            }
+        }
+        match savefile::load_file("Data/video_memory.bin", 1) {
+            Ok(video_memory) => {
+                self.video_memory = video_memory;
+            }
+            Err(e) => {
+                error!("Error loading file: {}", e);
+            }
+        }
         match savefile::load_file("Data/zoom.bin", 1) {
             Ok(zoom) => {
                 self.zoom = zoom;
@@ -364,59 +478,74 @@ impl Data {
                 error!("Error loading file: {}", e);
             }
         }
-        self.routines.clear();
-        for file in match fs::read_dir("./Data/routines") {Ok(file) => file,Err(e) => {println!("{e}");return}} {
-            let file = match file {
-                Ok(file) => file,
-                Err(e) => {
-                    error!("Error reading file: {}", e);
-                    continue;
-                }
-            };
-            let path = file.path();
-            let path = match path.to_str() {
-                Some(path) => path,
-                None => {
-                    error!("Error reading path");
-                    continue;
-                }
-            };
-            match savefile::load_file(path, 1) {
-                Ok(routine) => self.routines.push(routine),
-                Err(e) => {
-                    error!("Error loading file: {}", e);
-                    continue;
-                }
-            };
+        match savefile::load_file("Data/server.bin", 1) {
+            Ok(settings) => {
+                self.server_settings = settings;
+            }
+            Err(e) => {
+                error!("Error loading file: {}", e);
+            }
         }
-        self.judged.clear();
-        for file in match fs::read_dir("./Data/judge") {Ok(file) => file,Err(e) => {println!("{e}");return}} {
-            let file = match file {
-                Ok(file) => file,
-                Err(e) => {
-                    error!("Error reading file: {}", e);
-                    return;
-                }
-            };
-            let path = file.path();
-            let path = match path.to_str() {
-                Some(path) => path,
-                None => {
-                    error!("Error reading path");
-                    return;
-                }
-            };
-            match savefile::load_file(path, 1) {
-                Ok(routine) => self.judged.push(routine),
-                Err(e) => {
-                    error!("Error loading file: {}", e);
-                    return;
-                }
-            };
+        match savefile::load_file("Data/ffmpeg.bin", 1) {
+            Ok(path) => {
+                self.ffmpeg.path = path;
+            }
+            Err(e) => {
+                error!("Error loading file: {}", e);
+            }
         }
+        match savefile::load_file("Data/watch.bin", 1) {
+            Ok(settings) => {
+                self.watch.settings = settings;
+            }
+            Err(e) => {
+                error!("Error loading file: {}", e);
+            }
+        }
+        self.routines = load_folder("./Data/routines", 1);
+        self.judged = load_folder("./Data/judge", 5);
+        self.video_sessions = load_folder("./Data/videos", 8);
     }
 }
 
+/// Every file in `folder` that loads as a `T`. A missing folder or a bad file is logged
+/// and skipped, so the other folders still load.
+fn load_folder<T: savefile::WithSchema + savefile::Deserialize>(folder: &str, version: u32) -> Vec<T> {
+    let files = match fs::read_dir(folder) {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Error reading {}: {}", folder, e);
+            return vec![];
+        }
+    };
+    let mut loaded = vec![];
+    for file in files {
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Error reading file: {}", e);
+                continue;
+            }
+        };
+        let path = file.path();
+        let path = match path.to_str() {
+            Some(path) => path,
+            None => {
+                error!("Error reading path");
+                continue;
+            }
+        };
+        match savefile::load_file(path, version) {
+            Ok(value) => loaded.push(value),
+            Err(e) => {
+                error!("Error loading file: {}", e);
+                continue;
+            }
+        };
+    }
+    loaded
+}
+
 #[derive(Debug, Clone, Savefile, Eq, PartialEq)]
 enum Panel {
     Totals,
@@ -427,7 +556,7 @@ enum Panel {
     Execution,
     Deductions,
     Graph,
-
+    SignOff,
 }
 
 impl Default for Panel {
@@ -442,6 +571,10 @@ fn none_routine() -> Option<Routine> {
 
 fn false_func() -> bool{false}
 
+fn zero_func() -> f32 {0.0}
+
+fn empty_string() -> String {String::new()}
+
 #[derive(Debug, Clone, Savefile)]
 struct Judged {
     #[savefile_default_fn = "false_func"]
@@ -461,7 +594,35 @@ struct Judged {
     id: String,
     tof_total: f32,
     tof: [f32;10],
+    #[savefile_versions = "2.."]
+    #[savefile_default_fn = "empty_string"]
+    athlete: String,
+    #[savefile_versions = "2.."]
+    #[savefile_default_fn = "zero_func"]
+    penalty: f32,
+    #[savefile_versions = "3.."]
+    sign_off: Vec<SignOff>,
+    /// Path of the video the ToF was measured from.
+    #[savefile_versions = "4.."]
+    #[savefile_default_fn = "empty_string"]
+    video: String,
+    /// Take-off and landing times the ToF was measured from.
+    #[savefile_versions = "4.."]
+    video_points: Vec<f32>,
+    /// Faults marked on the video, kept as evidence for appeals.
+    #[savefile_versions = "5.."]
+    faults: Vec<Fault>,
+    #[savefile_ignore]
+    sign_off_name: String,
+    #[savefile_ignore]
+    sign_off_reason: String,
+}
 
+const GRAVITY: f32 = 9.81;
+
+/// Height of a ballistic flight lasting `tof` seconds, half the flight up and half down.
+fn flight_height(tof: f32) -> f32 {
+    GRAVITY * tof * tof / 8.0
 }
 
 impl Judged {
@@ -479,12 +640,83 @@ impl Judged {
             date_of_creation: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
             tof_total: 0.0,
             tof: [0.0;10],
+            athlete: String::new(),
+            penalty: 0.0,
+            sign_off: vec![],
+            video: String::new(),
+            video_points: vec![],
+            faults: vec![],
+            sign_off_name: String::new(),
+            sign_off_reason: String::new(),
+        }
+    }
+
+    fn load_routine(&mut self) {
+        match savefile::load_file(format!("Data/routines/{}.bin", self.routine_id), 1) {
+            Ok(routine) => {
+                self.routine = Some(routine);
+            }
+            Err(e) => {
+                error!("Error loading file: {}", e);
+            }
+        }
+    }
+
+    /// Sum of each judge's execution marks, all zero when judged by one judge.
+    fn judge_totals(&self) -> [f32; 5] {
+        let mut totals = [0.0; 5];
+        if self.five_judges {
+            for (k, marks) in self.execution_5.iter().enumerate() {
+                totals[k] = marks.iter().sum::<f32>();
+            }
+        }
+        totals
+    }
+
+    /// With five judges the highest and lowest totals are dropped and the middle three are summed.
+    fn execution_deduction(&self) -> f32 {
+        match self.five_judges {
+            false => self.execution_1.iter().sum::<f32>(),
+            true => {
+                let mut totals = self.judge_totals();
+                totals.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                totals[1..4].iter().sum::<f32>()
+            }
         }
     }
 
+    /// Each counting judge scores out of 10.
+    fn execution_score(&self) -> f32 {
+        match self.five_judges {
+            false => 10.0 - self.execution_deduction(),
+            true => 30.0 - self.execution_deduction(),
+        }
+    }
+
+    fn difficulty(&self) -> f32 {
+        match &self.routine {
+            Some(routine) => (routine.skills.iter().map(|s| (s.diff() * 100.0) as i32).sum::<i32>() as f32) / 100.0,
+            None => 0.0,
+        }
+    }
+
+    fn hd_total(&self) -> f32 {
+        self.hd.iter().sum::<f32>()
+    }
+
+    fn hd_score(&self) -> f32 {
+        10.0 - self.hd_total()
+    }
+
+    fn total(&self) -> f32 {
+        self.execution_score() + self.difficulty() + self.hd_score() + self.tof_total - self.penalty
+    }
+
     fn display(&mut self, egui_ctx:&egui::Context) {
+        let mut export = None;
+        let mut open = self.open;
         egui::Window::new(format!("Judged Routine: {}", self.id))
-            .open(&mut self.open)
+            .open(&mut open)
             .id(Id::new(&self.id)).show(egui_ctx, |ui| {
             egui::ScrollArea::horizontal().show(ui, |ui| {
 
@@ -578,11 +810,29 @@ impl Judged {
                                 self.panel = Panel::Totals;
                             });
                     }
+                    ui.separator();
+                    if self.panel == Panel::SignOff {
+                        ui.label("Sign-off");
+                    } else {
+                        ui.small_button(match self.locked() {
+                            true => format!("{} Sign-off", egui_phosphor::LOCK),
+                            false => "Sign-off".to_owned(),
+                        })
+                            .clicked()
+                            .then(|| {
+                                self.panel = Panel::SignOff;
+                            });
+                    }
                     
                 });
             });
             ui.separator();
 
+            if self.locked() && !matches!(self.panel, Panel::Totals | Panel::SignOff) {
+                ui.label(format!("{} Locked, re-open it in Sign-off to make changes", egui_phosphor::LOCK));
+                ui.set_enabled(false);
+            }
+
             match &self.panel {
                 &Panel::Routine => {
                     ui.small_button("reload").clicked().then(||{
@@ -595,6 +845,10 @@ impl Judged {
                     }
                 }
                         
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Athlete: ");
+                        ui.text_edit_singleline(&mut self.athlete);
                     });
                         for i in 0..10 {
                         ui.label(format!("{}.) {}",i+1,self.routine.as_ref().unwrap().skills[i].name()));
@@ -626,20 +880,28 @@ impl Judged {
                     }
                 }
                 &Panel::Execution => {
-                    ui.label(format!("Execution: -{:.2}", match self.five_judges {
-                        false => {self.execution_1.iter().sum::<f32>()},
-                        true => {
-                            let mut totals = self.execution_5.iter().map(|x| x.iter().sum::<f32>()).collect::<Vec<f32>>();
-                            totals.sort_by(|a,b| a.partial_cmp(b).unwrap());
-                            totals[1..4].iter().sum::<f32>()
-                        }
-                    }));
+                    ui.label(format!("Execution: -{:.2}", self.execution_deduction()));
+                    ui.checkbox(&mut self.five_judges, "Five judges");
                     if self.five_judges {
-                        for i in 0..10 {
-                        ui.selectable_label(self.execution_1[i] == 0.0, "0.0").clicked().then(|| {self.hd[i] = 0.0});
-                            ui.separator();
-                            ui.label("Unfinished");
-                        }
+                        egui::Grid::new("five judges").striped(true).show(ui, |ui| {
+                            ui.label("");
+                            for k in 0..5 {
+                                ui.label(format!("J{}", k + 1));
+                            }
+                            ui.end_row();
+                            for i in 0..10 {
+                                ui.label(format!("{}.) {}",i+1,self.routine.as_ref().unwrap().skills[i].name()));
+                                for k in 0..5 {
+                                    ui.add(egui::DragValue::new(&mut self.execution_5[k][i]).speed(0.1).clamp_range(0.0..=0.5).fixed_decimals(1));
+                                }
+                                ui.end_row();
+                            }
+                            ui.label("Total");
+                            for total in self.judge_totals() {
+                                ui.label(format!("-{:.1}", total));
+                            }
+                            ui.end_row();
+                        });
                     }else{
                         let mut total = 0.0;
                         for i in 0..10 {
@@ -659,14 +921,55 @@ impl Judged {
                         });
                     }
                 }
+                    if !self.faults.is_empty() {
+                        ui.collapsing(format!("{} Video evidence", egui_phosphor::FILE_VIDEO), |ui| {
+                            ui.label(&self.video);
+                            for fault in self.faults.iter() {
+                                ui.label(format!(
+                                    "{} {:.2}sec {} -{:.1} {}",
+                                    match element_at(&self.video_points, fault.time) {
+                                        Some(i) => format!("{}.)", i + 1),
+                                        None => "-".to_owned(),
+                                    },
+                                    fault.time,
+                                    fault.category.name(),
+                                    fault.deduction,
+                                    fault.note
+                                ));
+                            }
+                        });
+                    }
                 }
                 &Panel::Deductions => {
-                    ui.label("todo");
+                    ui.horizontal(|ui| {
+                        ui.label("Penalty ");
+                        ui.add(egui::DragValue::new(&mut self.penalty).speed(0.1).clamp_range(0.0..=10.0).fixed_decimals(1));
+                    });
                 }
                 &Panel::Totals => {
-                    ui.label("todo");
+                    ui.label(format!("E: {:.2}", self.execution_score()));
+                    ui.label(format!("D: {:.2}", self.difficulty()));
+                    ui.label(format!("H: {:.2}", self.hd_score()));
+                    ui.label(format!("T: {:.2}", self.tof_total));
+                    ui.label(format!("Penalty: -{:.2}", self.penalty));
+                    ui.separator();
+                    ui.heading(format!("Total: {:.2}", self.total()));
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.small_button(format!("{} Export CSV", egui_phosphor::FILE_CSV)).clicked().then(|| {
+                            export = Some(ExportFormat::Csv);
+                        });
+                        ui.small_button(format!("{} Export XLSX", egui_phosphor::FILE_XLS)).clicked().then(|| {
+                            export = Some(ExportFormat::Xlsx);
+                        });
+                    });
                 }
                 &Panel::TOF => {
+                            if !self.video.is_empty() {
+                                ui.label(format!("{} {}", egui_phosphor::FILE_VIDEO, self.video))
+                                    .on_hover_text(format!("{} points marked", self.video_points.len()));
+                                ui.separator();
+                            }
                             ui.horizontal(|ui| {
                                 ui.label("Total TOF ");
                             ui.add(egui::DragValue::new(&mut self.tof_total).speed(0.1).clamp_range(0.0..=50.0).fixed_decimals(2)
@@ -692,9 +995,13 @@ impl Judged {
                                     .suffix("sec")).changed() {
                                         self.tof_total = self.tof.iter().sum::<f32>();
                                     };
+                                ui.label(format!("{:.2}m", flight_height(self.tof[i])));
                                 });
                             }
                 }
+                &Panel::SignOff => {
+                    self.display_sign_off(ui);
+                }
                 &Panel::Graph => {
 
                         fn point_label(p: f64, _range: &RangeInclusive<f64>) -> String {
@@ -715,6 +1022,8 @@ impl Judged {
                                 plot_ui.hline(HLine::new(self.tof_total/10.0).name("average ToF").color(Color32::RED).style(LineStyle::Dashed { length: 5.0 }));
                                 let points: PlotPoints  = (0..10).map(|i| [i as f64, self.tof[i] as f64]).collect();
                                 plot_ui.line(Line::new(points).color(Color32::RED).name("ToF (sec)"));
+                                let points: PlotPoints  = (0..10).map(|i| [i as f64, flight_height(self.tof[i]) as f64]).collect();
+                                plot_ui.line(Line::new(points).color(Color32::GOLD).name("Height (m)"));
 
                                 if self.five_judges {
                                     for k in 0..5 {
@@ -741,6 +1050,10 @@ impl Judged {
         });
         ui.add_sized(ui.available_size(), egui::Label::new(""))
     });
+        self.open = open;
+        if let Some(format) = export {
+            export_dialog(std::slice::from_ref(self), format);
+        }
     }
     
 
@@ -751,19 +1064,14 @@ async fn main() {
     let mut  og_ppp = 0.0;
     let mut now = Instant::now();
     // get text input from user
-    let mut data = Data {
-        zoom: 1.0,
-        routines: vec![],
-        theme: WindowTheme::Light,
-        judged: vec![],
-    };
+    let mut data = Data::new();
     
     // let mut
     
     let mut fonts = egui::FontDefinitions::default();
-    ffmpeg_sidecar::download::auto_download().unwrap();
     
     data.load_files();
+    data.ffmpeg.check();
     egui_macroquad::ui(|egui_ctx| {
         data.theme.set_theme(egui_ctx);
         og_ppp = egui_ctx.pixels_per_point();
@@ -774,16 +1082,37 @@ async fn main() {
     });
 
     let mut videos: Vec<Video> = vec![];
+    let mut comparison = VideoComparison::new();
 
     loop {
         if now.elapsed().as_millis() > 1000 {
             data.save();
+            for v in videos.iter() {
+                v.save();
+            }
             now = Instant::now();
         }
 
-        clear_background(data.theme.bg());
+        data.update_server();
+        data.serve_api();
+        data.ffmpeg.update();
+        if data.ffmpeg.available() {
+            for path in data.watch.poll() {
+                videos.push(Video::watched(path, &data.watch.settings));
+            }
+        }
+
+        clear_background(match data.scoreboard.fullscreen {
+            true => data.scoreboard.theme.bg(),
+            false => data.theme.bg(),
+        });
         // Process keys, mouse etc.
         egui_macroquad::ui(|egui_ctx| {
+            // nothing but the board goes on the projector, marks still arrive through the server
+            if data.scoreboard.fullscreen {
+                data.scoreboard.display_fullscreen(egui_ctx, &data.judged);
+                return;
+            }
             data.theme.set_theme(egui_ctx);
 
             egui::SidePanel::left("Left").show(egui_ctx, |ui| {
@@ -808,13 +1137,33 @@ async fn main() {
                         data.judged.push(Judged::new());
                     });
                     
+                ui.button(format!("{} Judge Consistency", egui_phosphor::CHART_LINE))
+                    .clicked()
+                    .then(|| {
+                        data.judge_report.open = !data.judge_report.open;
+                    });
+
+                ui.menu_button(format!("{} Scoreboard", egui_phosphor::PROJECTOR_SCREEN), |ui| {
+                    for r in data.judged.iter_mut() {
+                        let name = format!("{} {}", r.athlete, r.date_of_creation);
+                        if ui.selectable_label(data.scoreboard.judged_id == r.id, name).clicked() {
+                            if r.routine.is_none() {
+                                r.load_routine();
+                            }
+                            data.scoreboard.judged_id = r.id.clone();
+                            data.scoreboard.open = true;
+                            ui.close_menu();
+                        }
+                    }
+                });
+
                 ui.collapsing( "Past Routines", |ui| {
                     let mut delete:Vec<usize> = vec![];
                     for (i, r) in data.judged.iter_mut().enumerate() {
                         let toggle = !r.open;
                         ui.horizontal(|ui| {
                             ui.selectable_value(&mut r.open, toggle, match &r.routine {Some(a) => &a.name, None => "None"});
-                            ui.small_button("Delete").on_hover_text("Waring! Permanent").clicked().then(|| {
+                            ui.add_enabled(!r.locked(), egui::Button::new("Delete").small()).on_hover_text("Waring! Permanent").clicked().then(|| {
                                 delete.push(i);
                                 match fs::remove_file(format!("Data/judged/{}.bin", r.id)) {
                                     Ok(_) => {},
@@ -843,29 +1192,81 @@ async fn main() {
                 });
                 ui.heading("Video");
                 ui.separator();
-                ui.button(format!("{} load video", egui_phosphor::FILE_VIDEO))
+                ui.add_enabled(data.ffmpeg.available(), egui::Button::new(format!("{} load video", egui_phosphor::FILE_VIDEO)))
+                    .on_disabled_hover_text("ffmpeg was not found, see Settings")
                     .clicked()
                     .then(|| {
                         videos.push(Video::new());
                     });
+                ui.add_enabled(data.ffmpeg.available(), egui::Button::new(format!("{} Compare videos", egui_phosphor::COLUMNS)))
+                    .on_disabled_hover_text("ffmpeg was not found, see Settings")
+                    .clicked()
+                    .then(|| {
+                        comparison.open = !comparison.open;
+                    });
                 ui.menu_button("reopen video", |ui| {
                     for r in videos.iter_mut() {
                         let toggle = !r.open;
                         ui.selectable_value(&mut r.open, toggle, &r.path);
                     }
+                    if !data.video_sessions.is_empty() {
+                        ui.separator();
+                        ui.label("Saved");
+                    }
+                    let mut reopen = None;
+                    let mut delete = None;
+                    for (i, s) in data.video_sessions.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add_enabled(data.ffmpeg.available(), egui::Button::new(&s.path))
+                                .on_hover_text(format!("{} points, ToF {:.2}sec", s.points.len(), s.tof_total))
+                                .on_disabled_hover_text("ffmpeg was not found, see Settings")
+                                .clicked()
+                                .then(|| {
+                                    reopen = Some(i);
+                                });
+                            ui.small_button("Delete").on_hover_text("Waring! Permanent").clicked().then(|| {
+                                delete = Some(i);
+                            });
+                        });
+                    }
+                    if let Some(i) = reopen {
+                        videos.push(Video::from_session(data.video_sessions.remove(i)));
+                        ui.close_menu();
+                    }
+                    if let Some(i) = delete {
+                        let s = data.video_sessions.remove(i);
+                        match fs::remove_file(format!("Data/videos/{}.bin", s.id)) {
+                            Ok(_) => {},
+                            Err(e) => {
+                                error!("Error deleting file: {}", e);
+                            }
+                        };
+                    }
                 });
-                ui.button(format!("{} Update/download ffmpeg", egui_phosphor::CLOUD_ARROW_DOWN))
-                    .clicked()
-                    .then(|| {
-                        ffmpeg_sidecar::download::auto_download().unwrap();
-                    });
                 ui.heading("Files");
                 ui.separator();
                 ui.button(format!("{} Save", egui_phosphor::FLOPPY_DISK))
                     .clicked()
                     .then(|| {
                         data.save();
+                        for v in videos.iter() {
+                            v.save();
+                        }
                     });
+                ui.menu_button(format!("{} Export Results", egui_phosphor::EXPORT), |ui| {
+                    let mut format = None;
+                    ui.button("CSV").clicked().then(|| format = Some(ExportFormat::Csv));
+                    ui.button("XLSX").clicked().then(|| format = Some(ExportFormat::Xlsx));
+                    if let Some(format) = format {
+                        for i in data.judged.iter_mut() {
+                            if i.routine.is_none() {
+                                i.load_routine();
+                            }
+                        }
+                        export_dialog(&data.judged, format);
+                        ui.close_menu();
+                    }
+                });
                 ui.heading("Settings");
                 ui.separator();
                 ui.menu_button(format!("{} UI Style", egui_phosphor::PALETTE), |ui| {
@@ -877,6 +1278,75 @@ async fn main() {
                     .then(|| {
                         data.theme.set_theme(egui_ctx);
                     });
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} Video memory ", egui_phosphor::CPU));
+                    ui.add(egui::DragValue::new(&mut data.video_memory).speed(16).clamp_range(128..=16384).suffix("MB"));
+                });
+                ui.collapsing(format!("{} ffmpeg", egui_phosphor::FILM_STRIP), |ui| {
+                    data.ffmpeg.display(ui);
+                });
+                ui.collapsing(format!("{} Watch folder", egui_phosphor::FOLDER_NOTCH_OPEN), |ui| {
+                    let watch = &mut data.watch.settings;
+                    ui.checkbox(&mut watch.enabled, "Open new recordings");
+                    ui.horizontal(|ui| {
+                        ui.label(match watch.folder.is_empty() {
+                            true => "No folder",
+                            false => &watch.folder,
+                        });
+                        ui.small_button(egui_phosphor::FOLDER_OPEN).on_hover_text("choose folder").clicked().then(|| {
+                            if let Ok(nfd2::Response::Okay(folder)) = nfd2::open_pick_folder(None) {
+                                watch.folder = folder.display().to_string();
+                            }
+                        });
+                    });
+                    egui::ComboBox::from_label("routine")
+                        .selected_text(match data.routines.iter().find(|r| r.id == watch.routine_id) {
+                            Some(r) => r.name.clone(),
+                            None => "None".to_owned(),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut watch.routine_id, String::new(), "None");
+                            for r in data.routines.iter() {
+                                ui.selectable_value(&mut watch.routine_id, r.id.clone(), &r.name);
+                            }
+                        });
+                    egui::ComboBox::from_label("athlete")
+                        .selected_text(match data.judged.iter().find(|j| j.id == watch.judged_id) {
+                            Some(j) => format!("{} {}", j.athlete, j.date_of_creation),
+                            None => "None".to_owned(),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut watch.judged_id, String::new(), "None");
+                            for j in data.judged.iter() {
+                                ui.selectable_value(&mut watch.judged_id, j.id.clone(), format!("{} {}", j.athlete, j.date_of_creation));
+                            }
+                        });
+                    ui.checkbox(&mut watch.analyse, "Detect take-offs from audio");
+                    if !data.watch.error.is_empty() {
+                        ui.colored_label(Color32::RED, &data.watch.error);
+                    } else if data.watch.settings.enabled && !data.ffmpeg.available() {
+                        ui.label("Waiting for ffmpeg");
+                    } else if data.watch.settings.enabled {
+                        ui.label(format!("Watching, {} opened", data.watch.imported));
+                    }
+                });
+                ui.collapsing(format!("{} HTTP API", egui_phosphor::GLOBE), |ui| {
+                    ui.checkbox(&mut data.server_settings.enabled, "Enabled");
+                    ui.horizontal(|ui| {
+                        ui.label("Address ");
+                        ui.add_enabled(data.server.is_none(), egui::TextEdit::singleline(&mut data.server_settings.address));
+                    });
+                    ui.checkbox(&mut data.server_settings.allow_writes, "Allow writes");
+                    ui.horizontal(|ui| {
+                        ui.label("Token ");
+                        ui.add(egui::TextEdit::singleline(&mut data.server_settings.token).password(true));
+                    });
+                    match &data.server {
+                        Some(server) => ui.label(format!("Listening on http://{}", server.address)),
+                        None if !data.server_error.is_empty() => ui.colored_label(Color32::RED, &data.server_error),
+                        None => ui.label("Stopped"),
+                    };
+                });
             });
 
             ui.horizontal(|ui| {
@@ -893,36 +1363,38 @@ async fn main() {
                 
             });
 
+            let budget = data.video_memory * 1024 * 1024 / videos.len().max(1);
             for i in videos.iter_mut() {
+                i.set_memory_budget(budget);
                 i.full_size = false;
                 if i.open {
-                    i.display(egui_ctx);
+                    i.display(egui_ctx, &mut data.judged, &data.routines);
                     if i.kill {
-                        for t in i.textures.iter_mut() {
-                            t.0.delete();
-                        }
-                        i.textures.clear();
+                        i.delete_textures();
                     }
                 }
             }
+            comparison.update(&mut videos);
+            comparison.display(egui_ctx, &mut videos);
             data.render(&egui_ctx);
         });
         });
+        for v in videos.iter().filter(|x| x.kill) {
+            v.save();
+            if !v.path.is_empty() {
+                data.video_sessions.push(v.session());
+            }
+        }
         videos.retain(|x| !x.kill);
 
         egui_macroquad::draw();
-        for v in &videos {
-            if v.show_video && v.open {
-                if v.textures.len() == 0 {
-                    break
+        if !data.scoreboard.fullscreen {
+            for v in &videos {
+                if v.show_video && v.open {
+                    v.draw(data.zoom);
                 }
-                let index = clamp(v.current_frame, 0, v.textures.len() - 1);
-                let frame = &v.textures[index];
-                draw_texture_ex(frame.0, v.rect[0] * data.zoom, v.rect[1] * data.zoom, WHITE, DrawTextureParams {
-                    dest_size: Some(vec2(v.rect[2]*data.zoom, v.rect[3]*data.zoom)),
-                    ..Default::default()
-                });
             }
+            comparison.draw(&videos, data.zoom);
         }
 
         next_frame().await;
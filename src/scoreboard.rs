@@ -0,0 +1,114 @@
+use egui::{ Align, Layout, RichText };
+use strum::IntoEnumIterator;
+
+use crate::{ Judged, WindowTheme };
+
+fn set_fullscreen(fullscreen: bool) {
+    unsafe {
+        macroquad::window::get_internal_gl().quad_context.set_fullscreen(fullscreen);
+    }
+}
+
+pub struct Scoreboard {
+    pub open: bool,
+    pub fullscreen: bool,
+    /// Id of the `Judged` record being shown.
+    pub judged_id: String,
+    pub theme: WindowTheme,
+}
+
+impl Scoreboard {
+    pub fn new() -> Scoreboard {
+        Scoreboard {
+            open: false,
+            fullscreen: false,
+            judged_id: String::new(),
+            theme: WindowTheme::Dark,
+        }
+    }
+
+    fn board(&self, ui: &mut egui::Ui, judged: &[Judged], size: f32) {
+        let judged = match judged.iter().find(|j| j.id == self.judged_id) {
+            Some(j) => j,
+            None => {
+                ui.label(RichText::new("No routine selected").size(size));
+                return;
+            }
+        };
+        let routine_name = match &judged.routine {
+            Some(r) => r.name.clone(),
+            None => "".to_owned(),
+        };
+        ui.with_layout(Layout::top_down(Align::Center), |ui| {
+            ui.label(RichText::new(&judged.athlete).size(size * 1.5).strong());
+            ui.label(RichText::new(routine_name).size(size));
+            ui.add_space(size);
+            ui.columns(4, |columns| {
+                for (column, (name, score)) in columns.iter_mut().zip([
+                    ("E", judged.execution_score()),
+                    ("D", judged.difficulty()),
+                    ("H", judged.hd_score()),
+                    ("T", judged.tof_total),
+                ]) {
+                    column.with_layout(Layout::top_down(Align::Center), |ui| {
+                        ui.label(RichText::new(name).size(size * 0.8).weak());
+                        ui.label(RichText::new(format!("{:.2}", score)).size(size * 1.2).monospace());
+                    });
+                }
+            });
+            if judged.penalty != 0.0 {
+                ui.label(RichText::new(format!("Penalty -{:.2}", judged.penalty)).size(size * 0.8));
+            }
+            ui.add_space(size);
+            ui.label(RichText::new(format!("{:.3}", judged.total())).size(size * 3.0).strong().monospace());
+        });
+    }
+
+    pub fn display(&mut self, egui_ctx: &egui::Context, judged: &[Judged]) {
+        let mut open = self.open;
+        egui::Window
+            ::new("Scoreboard")
+            .open(&mut open)
+            .default_width(600.0)
+            .show(egui_ctx, |ui| {
+                self.board(ui, judged, 24.0);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    egui::ComboBox
+                        ::from_id_source("scoreboard theme")
+                        .selected_text(format!("{:?}", self.theme))
+                        .show_ui(ui, |ui| {
+                            for s in WindowTheme::iter() {
+                                ui.selectable_value(&mut self.theme, s, format!("{:?}", s));
+                            }
+                        });
+                    let label = match self.fullscreen {
+                        true => format!("{} Leave Full Screen", egui_phosphor::CORNERS_IN),
+                        false => format!("{} Full Screen", egui_phosphor::CORNERS_OUT),
+                    };
+                    ui.small_button(label)
+                        .clicked()
+                        .then(|| {
+                            self.fullscreen = !self.fullscreen;
+                            set_fullscreen(self.fullscreen);
+                        });
+                });
+            });
+        self.open = open;
+    }
+
+    /// Fills the whole window in place of the rest of the app; press escape to leave.
+    pub fn display_fullscreen(&mut self, egui_ctx: &egui::Context, judged: &[Judged]) {
+        // the app theme is set again at the start of the next frame
+        self.theme.set_theme(egui_ctx);
+        egui::CentralPanel::default().show(egui_ctx, |ui| {
+            let size = ui.available_height() / 16.0;
+            ui.add_space(size);
+            self.board(ui, judged, size);
+        });
+        if macroquad::input::is_key_pressed(macroquad::input::KeyCode::Escape) {
+            self.fullscreen = false;
+            set_fullscreen(false);
+        }
+    }
+}
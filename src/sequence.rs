@@ -0,0 +1,250 @@
+use std::path::{ Path, PathBuf };
+use ffmpeg_sidecar::command::FfmpegCommand;
+
+/// Frame rate of image sequences until the user picks one.
+pub const IMAGE_FPS: f32 = 30.0;
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "bmp", "tif", "tiff", "webp"];
+
+/// What ffmpeg reads frames from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    File(String),
+    /// Numbered images, read with ffmpeg's image2 demuxer at `fps`.
+    Images {
+        /// Path with the frame number replaced by a `%d` style placeholder.
+        pattern: String,
+        start: usize,
+        frames: usize,
+        fps: f32,
+    },
+}
+
+impl Source {
+    /// A video file, or the image sequence `path` points at: a folder, a glob like
+    /// `shots/*.png` or any one image of the sequence.
+    pub fn open(path: &str, fps: f32) -> Result<Source, String> {
+        let p = Path::new(path);
+        let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        // the run of numbers to open when one image was picked
+        let mut picked = None;
+        let files = if p.is_dir() {
+            images_in(p, |_| true)?
+        } else if name.contains('*') || name.contains('?') {
+            let folder = p.parent().filter(|f| !f.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            images_in(folder, |file| glob_match(&name, file))?
+        } else if is_image(p) {
+            let folder = p.parent().filter(|f| !f.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let (prefix, digits, suffix) = split_number(&name).ok_or(format!("{} is not numbered", name))?;
+            picked = digits.parse::<usize>().ok();
+            images_in(folder, |file| match split_number(file) {
+                Some((p, _, s)) => p == prefix && s == suffix,
+                None => false,
+            })?
+        } else {
+            return Ok(Source::File(path.to_owned()));
+        };
+        sequence(files, fps, picked)
+    }
+
+    pub fn is_images(&self) -> bool {
+        matches!(self, Source::Images { .. })
+    }
+
+    /// Seconds of video, known up front for image sequences.
+    pub fn duration(&self) -> Option<f32> {
+        match self {
+            Source::File(_) => None,
+            Source::Images { frames, fps, .. } => Some((*frames as f32) / fps),
+        }
+    }
+
+    /// Adds this source as the input of `command`.
+    pub fn input<'a>(&self, command: &'a mut FfmpegCommand) -> &'a mut FfmpegCommand {
+        match self {
+            Source::File(path) => command.input(path),
+            Source::Images { pattern, start, fps, .. } =>
+                command
+                    .args(["-f", "image2", "-framerate", &fps.to_string(), "-start_number", &start.to_string()])
+                    .input(pattern),
+        }
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    match path.extension() {
+        Some(ext) => IMAGE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+fn images_in(folder: &Path, keep: impl Fn(&str) -> bool) -> Result<Vec<PathBuf>, String> {
+    let files = std::fs
+        ::read_dir(folder)
+        .map_err(|e| format!("{}: {}", folder.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && is_image(path))
+        .filter(|path| keep(&path.file_name().unwrap_or_default().to_string_lossy()))
+        .collect::<Vec<PathBuf>>();
+    match files.is_empty() {
+        true => Err(format!("No images in {}", folder.display())),
+        false => Ok(files),
+    }
+}
+
+/// `*` and `?` wildcards, nothing else.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+/// A file name around its last run of digits, like `("shot_", "0042", ".png")`.
+fn split_number(name: &str) -> Option<(&str, &str, &str)> {
+    let end = name.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = name[..end].rfind(|c: char| !c.is_ascii_digit()).map_or(0, |i| i + 1);
+    Some((&name[..start], &name[start..end], &name[end..]))
+}
+
+/// The run of consecutive numbers containing `picked`, or else the longest run, of the
+/// images sharing the first image's name.
+fn sequence(files: Vec<PathBuf>, fps: f32, picked: Option<usize>) -> Result<Source, String> {
+    let mut numbered = files
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            let (prefix, digits, suffix) = split_number(&name)?;
+            Some((prefix.to_owned(), digits.to_owned(), suffix.to_owned(), digits.parse::<usize>().ok()?))
+        })
+        .collect::<Vec<_>>();
+    numbered.sort_by_key(|(prefix, _, suffix, number)| (prefix.clone(), suffix.clone(), *number));
+    let (prefix, _, suffix, _) = numbered.first().cloned().ok_or("The images are not numbered")?;
+    numbered.retain(|(p, _, s, _)| *p == prefix && *s == suffix);
+
+    // start and length of each run
+    let mut runs = vec![(0, 1)];
+    for i in 1..numbered.len() {
+        match numbered[i].3 == numbered[i - 1].3 + 1 {
+            true => {
+                runs.last_mut().unwrap().1 += 1;
+            }
+            false => runs.push((i, 1)),
+        }
+    }
+    let contains = |(start, len): &(usize, usize), n: usize| numbered[*start].3 <= n && n <= numbered[start + len - 1].3;
+    let (start, len) = match picked.and_then(|n| runs.iter().find(|run| contains(run, n))) {
+        Some(run) => *run,
+        // the first of the longest
+        None => *runs.iter().rev().max_by_key(|(_, len)| *len).unwrap(),
+    };
+    let images = &numbered[start..start + len];
+    let width = images[0].1.len();
+    let placeholder = match images.iter().all(|(_, digits, _, _)| digits.len() == width) {
+        true => format!("%0{}d", width),
+        false if images.iter().all(|(_, digits, _, _)| !digits.starts_with('0') || digits.len() == 1) => "%d".to_owned(),
+        false => {
+            return Err("The images are numbered with different widths".to_owned());
+        }
+    };
+    // a literal % in the path would be read as another placeholder
+    let escape = |s: &str| s.replace('%', "%%");
+    let folder = escape(&files[0].parent().unwrap_or(Path::new(".")).display().to_string());
+    let name = format!("{}{}{}", escape(&prefix), placeholder, escape(&suffix));
+    Ok(Source::Images {
+        pattern: Path::new(&folder).join(name).display().to_string(),
+        start: images[0].3,
+        frames: images.len(),
+        fps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(|n| Path::new("shots").join(n)).collect()
+    }
+
+    fn pattern(name: &str) -> String {
+        Path::new("shots").join(name).display().to_string()
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("*.png", "shot_001.png"));
+        assert!(glob_match("shot_???.png", "shot_001.png"));
+        assert!(glob_match("*_*.png", "a_b_c.png"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*", "a"));
+        assert!(!glob_match("*.png", "shot_001.jpg"));
+        assert!(!glob_match("shot_??.png", "shot_001.png"));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(split_number("shot_0042.png"), Some(("shot_", "0042", ".png")));
+        assert_eq!(split_number("take2_0042.png"), Some(("take2_", "0042", ".png")));
+        assert_eq!(split_number("7.jpg"), Some(("", "7", ".jpg")));
+        assert_eq!(split_number("v2 final.tif"), Some(("v", "2", " final.tif")));
+        assert_eq!(split_number("shot.png"), None);
+    }
+
+    #[test]
+    fn padded() {
+        let source = sequence(files(&["f_0009.png", "f_0010.png", "f_0011.png"]), 25.0, None).unwrap();
+        assert_eq!(source, Source::Images { pattern: pattern("f_%04d.png"), start: 9, frames: 3, fps: 25.0 });
+    }
+
+    #[test]
+    fn unpadded() {
+        let source = sequence(files(&["10.png", "8.png", "9.png"]), 25.0, None).unwrap();
+        assert_eq!(source, Source::Images { pattern: pattern("%d.png"), start: 8, frames: 3, fps: 25.0 });
+    }
+
+    #[test]
+    fn mixed_widths() {
+        assert!(sequence(files(&["f_009.png", "f_10.png"]), 25.0, None).is_err());
+        assert!(sequence(files(&["shot.png"]), 25.0, None).is_err());
+    }
+
+    #[test]
+    fn percent_is_escaped() {
+        let source = sequence(files(&["100%_1.png", "100%_2.png"]), 25.0, None).unwrap();
+        assert_eq!(source, Source::Images { pattern: pattern("100%%_%01d.png"), start: 1, frames: 2, fps: 25.0 });
+    }
+
+    #[test]
+    fn gaps() {
+        let names = ["a1.png", "a2.png", "a5.png", "a6.png", "a7.png", "a9.png"];
+        let longest = sequence(files(&names), 25.0, None).unwrap();
+        assert_eq!(longest, Source::Images { pattern: pattern("a%01d.png"), start: 5, frames: 3, fps: 25.0 });
+        let picked = sequence(files(&names), 25.0, Some(2)).unwrap();
+        assert_eq!(picked, Source::Images { pattern: pattern("a%01d.png"), start: 1, frames: 2, fps: 25.0 });
+        let single = sequence(files(&names), 25.0, Some(9)).unwrap();
+        assert_eq!(single, Source::Images { pattern: pattern("a%01d.png"), start: 9, frames: 1, fps: 25.0 });
+    }
+
+    #[test]
+    fn other_names_are_ignored() {
+        let source = sequence(files(&["b_1.png", "a_1.png", "a_2.png", "a_3.png"]), 25.0, None).unwrap();
+        assert_eq!(source, Source::Images { pattern: pattern("a_%01d.png"), start: 1, frames: 3, fps: 25.0 });
+    }
+}
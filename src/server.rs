@@ -0,0 +1,475 @@
+use std::{ sync::{ mpsc, Arc }, time::Duration };
+use macroquad::logging::error;
+use serde_json::{ json, Value };
+use tiny_http::{ Header, Method, Response };
+
+use crate::{ Data, Judged, Routine };
+
+#[derive(Debug, Clone, Savefile, PartialEq)]
+pub struct ServerSettings {
+    pub enabled: bool,
+    pub address: String,
+    pub allow_writes: bool,
+    /// Writes must send this in an `X-Tramp-Token` header, unless it is empty.
+    pub token: String,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            enabled: false,
+            address: "127.0.0.1:7878".to_owned(),
+            allow_writes: false,
+            token: String::new(),
+        }
+    }
+}
+
+/// A request handed from the server thread to the ui thread, which owns the data.
+pub struct ApiRequest {
+    pub method: Method,
+    pub path: String,
+    pub body: String,
+    pub token: Option<String>,
+    respond: mpsc::Sender<(u16, Value)>,
+}
+
+pub struct Server {
+    server: Arc<tiny_http::Server>,
+    requests: mpsc::Receiver<ApiRequest>,
+    pub address: String,
+}
+
+impl Server {
+    pub fn start(address: &str) -> Result<Server, String> {
+        let server = Arc::new(tiny_http::Server::http(address).map_err(|e| e.to_string())?);
+        let (tx, requests) = mpsc::channel();
+        let listener = server.clone();
+        std::thread::spawn(move || {
+            for mut request in listener.incoming_requests() {
+                let mut body = String::new();
+                if let Err(e) = request.as_reader().read_to_string(&mut body) {
+                    error!("Error reading request: {}", e);
+                }
+                let token = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("X-Tramp-Token"))
+                    .map(|h| h.value.as_str().to_owned());
+                let (respond, response) = mpsc::channel();
+                let api_request = ApiRequest {
+                    method: request.method().clone(),
+                    path: request.url().to_owned(),
+                    body,
+                    token,
+                    respond,
+                };
+                if tx.send(api_request).is_err() {
+                    break;
+                }
+                let (status, value) = response
+                    .recv_timeout(Duration::from_secs(5))
+                    .unwrap_or((503, json!({ "error": "tramp is not responding" })));
+                let response = Response::from_string(value.to_string())
+                    .with_status_code(status)
+                    .with_header(
+                        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()
+                    );
+                if let Err(e) = request.respond(response) {
+                    error!("Error sending response: {}", e);
+                }
+            }
+        });
+        Ok(Server {
+            server,
+            requests,
+            address: address.to_owned(),
+        })
+    }
+
+    /// Requests waiting to be answered, call once per frame.
+    pub fn pending(&self) -> Vec<ApiRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+/// A time of flight sent to the API, `None` unless it is a finite number of seconds.
+fn tof_value(value: &Value) -> Option<f32> {
+    let tof = value.as_f64()? as f32;
+    match tof.is_finite() && tof >= 0.0 {
+        true => Some(tof),
+        false => None,
+    }
+}
+
+/// Routines are only needed for the records a request returns, and the loaded ones are
+/// already in memory.
+fn attach_routine(routines: &[Routine], judged: &mut Judged) {
+    if judged.routine.is_none() {
+        judged.routine = routines.iter().find(|r| r.id == judged.routine_id).cloned();
+    }
+}
+
+fn routine_json(routine: &Routine) -> Value {
+    json!({
+        "id": routine.id,
+        "name": routine.name,
+        "difficulty": routine.skills.iter().map(|s| s.diff()).sum::<f32>(),
+        "skills": routine.skills.iter().map(|s| json!({
+            "name": s.name(),
+            "notation": s.notation(),
+            "difficulty": s.diff(),
+        })).collect::<Vec<Value>>(),
+    })
+}
+
+fn totals_json(judged: &Judged) -> Value {
+    json!({
+        "execution": judged.execution_score(),
+        "difficulty": judged.difficulty(),
+        "hd": judged.hd_score(),
+        "tof": judged.tof_total,
+        "penalty": judged.penalty,
+        "total": judged.total(),
+    })
+}
+
+fn judged_summary_json(judged: &Judged) -> Value {
+    json!({
+        "id": judged.id,
+        "athlete": judged.athlete,
+        "routine_id": judged.routine_id,
+        "routine": judged.routine.as_ref().map(|r| r.name.clone()),
+        "date": judged.date_of_creation,
+        "totals": totals_json(judged),
+    })
+}
+
+fn judged_json(judged: &Judged) -> Value {
+    let mut value = judged_summary_json(judged);
+    value["five_judges"] = json!(judged.five_judges);
+    value["execution"] = match judged.five_judges {
+        true => json!(judged.execution_5),
+        false => json!(judged.execution_1),
+    };
+    value["hd"] = json!(judged.hd);
+    value["tof"] = json!(judged.tof);
+    value["locked"] = json!(judged.locked());
+    value
+}
+
+impl Data {
+    /// Starts or stops the server to match the settings.
+    pub fn update_server(&mut self) {
+        let running = match &self.server {
+            Some(server) => server.address == self.server_settings.address,
+            None => false,
+        };
+        if self.server_settings.enabled && !running {
+            self.server = None;
+            match Server::start(&self.server_settings.address) {
+                Ok(server) => {
+                    self.server_error = String::new();
+                    self.server = Some(server);
+                }
+                Err(e) => {
+                    error!("Error starting server: {}", e);
+                    self.server_error = e;
+                    self.server_settings.enabled = false;
+                }
+            }
+        }
+        if !self.server_settings.enabled && self.server.is_some() {
+            self.server = None;
+        }
+    }
+
+    pub fn serve_api(&mut self) {
+        let requests = match &self.server {
+            Some(server) => server.pending(),
+            None => {
+                return;
+            }
+        };
+        for request in requests {
+            let response = self.handle_api(&request);
+            request.respond.send(response).ok();
+        }
+    }
+
+    fn handle_api(&mut self, request: &ApiRequest) -> (u16, Value) {
+        let path = request.path.split('?').next().unwrap_or("");
+        let parts = path
+            .split('/')
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<&str>>();
+        match (&request.method, parts.as_slice()) {
+            (Method::Get, ["routines"]) => (200, json!(self.routines.iter().map(routine_json).collect::<Vec<Value>>())),
+            (Method::Get, ["routines", id]) => {
+                match self.routines.iter().find(|r| &r.id == id) {
+                    Some(r) => (200, routine_json(r)),
+                    None => (404, json!({ "error": "no such routine" })),
+                }
+            }
+            (Method::Get, ["athletes"]) => {
+                let mut athletes = self.judged
+                    .iter()
+                    .map(|j| j.athlete.clone())
+                    .filter(|a| !a.is_empty())
+                    .collect::<Vec<String>>();
+                athletes.sort();
+                athletes.dedup();
+                let athletes = athletes
+                    .iter()
+                    .map(|a| json!({
+                        "name": a,
+                        "judged": self.judged.iter().filter(|j| &j.athlete == a).map(|j| j.id.clone()).collect::<Vec<String>>(),
+                    }))
+                    .collect::<Vec<Value>>();
+                (200, json!(athletes))
+            }
+            (Method::Get, ["judged"]) => {
+                for j in self.judged.iter_mut() {
+                    attach_routine(&self.routines, j);
+                }
+                (200, json!(self.judged.iter().map(judged_summary_json).collect::<Vec<Value>>()))
+            }
+            (Method::Get, ["judged", id]) => {
+                match self.judged.iter_mut().find(|j| &j.id == id) {
+                    Some(j) => {
+                        attach_routine(&self.routines, j);
+                        (200, judged_json(j))
+                    }
+                    None => (404, json!({ "error": "no such judged routine" })),
+                }
+            }
+            (Method::Get, ["live"]) => {
+                match self.judged.iter_mut().find(|j| j.id == self.scoreboard.judged_id) {
+                    Some(j) => {
+                        attach_routine(&self.routines, j);
+                        (200, judged_summary_json(j))
+                    }
+                    None => (404, json!({ "error": "no routine on the scoreboard" })),
+                }
+            }
+            (Method::Post, ["judged", id, "tof"]) => {
+                if !self.server_settings.allow_writes {
+                    return (403, json!({ "error": "writes are disabled" }));
+                }
+                if
+                    !self.server_settings.token.is_empty() &&
+                    request.token.as_ref() != Some(&self.server_settings.token)
+                {
+                    return (401, json!({ "error": "bad token" }));
+                }
+                let body: Value = match serde_json::from_str(&request.body) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        return (400, json!({ "error": e.to_string() }));
+                    }
+                };
+                let tof = match body["tof"].as_array() {
+                    Some(tof) if tof.len() <= 10 => tof,
+                    _ => {
+                        return (400, json!({ "error": "expected up to ten values in \"tof\"" }));
+                    }
+                };
+                let tof = match tof.iter().map(tof_value).collect::<Option<Vec<f32>>>() {
+                    Some(tof) => tof,
+                    None => {
+                        return (400, json!({ "error": "\"tof\" values must be non-negative numbers" }));
+                    }
+                };
+                let total = match body.get("total") {
+                    Some(total) =>
+                        match tof_value(total) {
+                            Some(total) => Some(total),
+                            None => {
+                                return (400, json!({ "error": "\"total\" must be a non-negative number" }));
+                            }
+                        }
+                    None => None,
+                };
+                let judged = match self.judged.iter_mut().find(|j| &j.id == id) {
+                    Some(j) => j,
+                    None => {
+                        return (404, json!({ "error": "no such judged routine" }));
+                    }
+                };
+                if judged.locked() {
+                    return (409, json!({ "error": "judged routine is locked" }));
+                }
+                judged.tof = [0.0; 10];
+                judged.tof[..tof.len()].copy_from_slice(&tof);
+                judged.tof_total = total.unwrap_or(judged.tof.iter().sum::<f32>());
+                attach_routine(&self.routines, judged);
+                (200, judged_json(judged))
+            }
+            _ => (404, json!({ "error": "not found" })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ SignOff, SignOffKind };
+
+    fn data() -> Data {
+        let mut data = Data::new();
+        data.server_settings.allow_writes = true;
+        let mut routine = Routine::blank();
+        routine.id = "routine".to_owned();
+        routine.name = "Final".to_owned();
+        data.routines.push(routine);
+        for id in ["a", "b"] {
+            let mut judged = Judged::new();
+            judged.id = id.to_owned();
+            judged.routine_id = "routine".to_owned();
+            data.judged.push(judged);
+        }
+        data
+    }
+
+    fn call(data: &mut Data, method: Method, path: &str, body: &str) -> (u16, Value) {
+        let (respond, _) = mpsc::channel();
+        data.handle_api(
+            &(ApiRequest {
+                method,
+                path: path.to_owned(),
+                body: body.to_owned(),
+                token: None,
+                respond,
+            })
+        )
+    }
+
+    #[test]
+    fn get_judged() {
+        let mut data = data();
+        let (status, value) = call(&mut data, Method::Get, "/judged/a", "");
+        assert_eq!(status, 200);
+        assert_eq!(value["routine"], "Final");
+        assert_eq!(value["tof"].as_array().unwrap().len(), 10);
+        // only the record asked for gets its routine
+        assert!(data.judged[1].routine.is_none());
+        assert_eq!(call(&mut data, Method::Get, "/judged/c", "").0, 404);
+    }
+
+    #[test]
+    fn post_tof() {
+        let mut data = data();
+        let (status, value) = call(&mut data, Method::Post, "/judged/a/tof", r#"{"tof": [1.5, 1.25]}"#);
+        assert_eq!(status, 200);
+        assert_eq!(value["tof"][1], 1.25);
+        assert_eq!(data.judged[0].tof[..3], [1.5, 1.25, 0.0]);
+        assert_eq!(data.judged[0].tof_total, 2.75);
+
+        let (status, _) = call(&mut data, Method::Post, "/judged/a/tof", r#"{"tof": [1.5], "total": 14.5}"#);
+        assert_eq!(status, 200);
+        assert_eq!(data.judged[0].tof_total, 14.5);
+        assert_eq!(data.judged[0].tof[1], 0.0);
+    }
+
+    #[test]
+    fn post_tof_rejects_bad_values() {
+        let mut data = data();
+        call(&mut data, Method::Post, "/judged/a/tof", r#"{"tof": [1.5]}"#);
+        for body in [
+            "not json",
+            r#"{"tof": 1.5}"#,
+            r#"{"tof": [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]}"#,
+            r#"{"tof": ["abc"]}"#,
+            r#"{"tof": [null]}"#,
+            r#"{"tof": [true]}"#,
+            r#"{"tof": [-1.0]}"#,
+            r#"{"tof": [1e300]}"#,
+            r#"{"tof": [1.0], "total": "abc"}"#,
+            r#"{"tof": [1.0], "total": null}"#,
+            r#"{"tof": [1.0], "total": -3}"#,
+        ] {
+            assert_eq!(call(&mut data, Method::Post, "/judged/a/tof", body).0, 400, "{}", body);
+        }
+        assert_eq!(data.judged[0].tof[0], 1.5);
+        assert_eq!(data.judged[0].tof_total, 1.5);
+    }
+
+    #[test]
+    fn post_tof_missing_or_locked() {
+        let mut data = data();
+        assert_eq!(call(&mut data, Method::Post, "/judged/c/tof", r#"{"tof": [1.5]}"#).0, 404);
+        data.judged[1].sign_off.push(SignOff {
+            kind: SignOffKind::Locked,
+            name: "Chair".to_owned(),
+            reason: String::new(),
+            time: String::new(),
+            hash: String::new(),
+        });
+        assert_eq!(call(&mut data, Method::Post, "/judged/b/tof", r#"{"tof": [1.5]}"#).0, 409);
+        assert_eq!(data.judged[1].tof[0], 0.0);
+    }
+
+    #[test]
+    fn post_tof_needs_permission() {
+        let mut data = data();
+        data.server_settings.allow_writes = false;
+        assert_eq!(call(&mut data, Method::Post, "/judged/a/tof", r#"{"tof": [1.5]}"#).0, 403);
+        data.server_settings.allow_writes = true;
+        data.server_settings.token = "secret".to_owned();
+        assert_eq!(call(&mut data, Method::Post, "/judged/a/tof", r#"{"tof": [1.5]}"#).0, 401);
+    }
+
+    /// Sends `request` to `address` over TCP, returning the status, headers and body.
+    fn http(address: std::net::SocketAddr, request: &str) -> (u16, String, String) {
+        use std::io::{ Read, Write };
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, head.to_owned(), body.to_owned())
+    }
+
+    #[test]
+    fn serves_over_http() {
+        let mut data = data();
+        assert!(ServerSettings::default().address.starts_with("127.0.0.1:"));
+        data.server_settings.enabled = true;
+        data.server_settings.address = "127.0.0.1:0".to_owned();
+        data.update_server();
+        let address = data.server.as_ref().unwrap().server.server_addr().to_ip().unwrap();
+        assert!(address.ip().is_loopback());
+
+        let client = std::thread::spawn(move || {
+            [
+                "GET /judged/a HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                "GET /judged/c HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            ].map(|request| http(address, request))
+        });
+        // answered on this thread, like the ui thread does every frame
+        while !client.is_finished() {
+            data.serve_api();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let [found, missing] = client.join().unwrap();
+
+        assert_eq!(found.0, 200);
+        assert!(found.1.lines().any(|h| h.eq_ignore_ascii_case("Content-Type: application/json")), "{}", found.1);
+        let value: Value = serde_json::from_str(&found.2).unwrap();
+        assert_eq!(value["id"], "a");
+        assert_eq!(value["routine"], "Final");
+        assert_eq!(value["totals"]["total"], json!(data.judged[0].total()));
+
+        assert_eq!(missing.0, 404);
+        let value: Value = serde_json::from_str(&missing.2).unwrap();
+        assert_eq!(value["error"], "no such judged routine");
+    }
+}
@@ -0,0 +1,184 @@
+use egui::Color32;
+use sha2::{ Digest, Sha256 };
+
+use crate::Judged;
+
+#[derive(Debug, Clone, Copy, Savefile, PartialEq, Eq)]
+pub enum SignOffKind {
+    Locked,
+    Reopened,
+}
+
+/// One entry in the sign-off history of a judged routine. Entries are only ever appended.
+#[derive(Debug, Clone, Savefile, PartialEq)]
+pub struct SignOff {
+    pub kind: SignOffKind,
+    /// The chair locking the routine, or whoever re-opened it.
+    pub name: String,
+    /// Why a locked routine was re-opened, empty when locking.
+    pub reason: String,
+    pub time: String,
+    /// Hash of the judged content at the time of the entry.
+    pub hash: String,
+}
+
+impl Judged {
+    /// Hash of everything a judge can change, so edits made after sign-off show up.
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.id.as_bytes());
+        hasher.update(self.routine_id.as_bytes());
+        hasher.update(self.athlete.as_bytes());
+        hasher.update(self.date_of_creation.as_bytes());
+        hasher.update([self.five_judges as u8]);
+        let totals = [self.tof_total, self.penalty];
+        let marks = self.execution_1
+            .iter()
+            .chain(self.execution_5.iter().flatten())
+            .chain(self.hd.iter())
+            .chain(self.tof.iter())
+            .chain(totals.iter());
+        for mark in marks {
+            hasher.update(mark.to_bits().to_le_bytes());
+        }
+        // nothing is added without faults, so routines locked before faults existed still match
+        for fault in self.faults.iter() {
+            hasher.update(fault.time.to_bits().to_le_bytes());
+            hasher.update([fault.category as u8]);
+            hasher.update(fault.deduction.to_bits().to_le_bytes());
+            hasher.update((fault.note.len() as u64).to_le_bytes());
+            hasher.update(fault.note.as_bytes());
+        }
+        // the video and its take-offs and landings are the evidence behind the ToF
+        if !self.video.is_empty() || !self.video_points.is_empty() {
+            hasher.update((self.video.len() as u64).to_le_bytes());
+            hasher.update(self.video.as_bytes());
+            hasher.update((self.video_points.len() as u64).to_le_bytes());
+            for point in self.video_points.iter() {
+                hasher.update(point.to_bits().to_le_bytes());
+            }
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn locked(&self) -> bool {
+        match self.sign_off.last() {
+            Some(entry) => entry.kind == SignOffKind::Locked,
+            None => false,
+        }
+    }
+
+    /// True when a locked routine no longer matches the hash stored at sign-off.
+    pub fn tampered(&self) -> bool {
+        match self.sign_off.last() {
+            Some(entry) if entry.kind == SignOffKind::Locked => entry.hash != self.content_hash(),
+            _ => false,
+        }
+    }
+
+    fn log_sign_off(&mut self, kind: SignOffKind, name: String, reason: String) {
+        self.sign_off.push(SignOff {
+            kind,
+            name,
+            reason,
+            time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            hash: self.content_hash(),
+        });
+    }
+
+    pub fn display_sign_off(&mut self, ui: &mut egui::Ui) {
+        if self.locked() {
+            if self.tampered() {
+                ui.colored_label(Color32::RED, "Modified since sign-off!");
+            } else {
+                ui.colored_label(Color32::GREEN, "Locked, content matches sign-off");
+            }
+            ui.horizontal(|ui| {
+                ui.label("Name ");
+                ui.text_edit_singleline(&mut self.sign_off_name);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Reason ");
+                ui.text_edit_singleline(&mut self.sign_off_reason);
+            });
+            let valid = !self.sign_off_name.trim().is_empty() && !self.sign_off_reason.trim().is_empty();
+            if ui.add_enabled(valid, egui::Button::new(format!("{} Re-open", egui_phosphor::LOCK_OPEN))).clicked() {
+                let name = std::mem::take(&mut self.sign_off_name);
+                let reason = std::mem::take(&mut self.sign_off_reason);
+                self.log_sign_off(SignOffKind::Reopened, name, reason);
+            }
+        } else {
+            ui.horizontal(|ui| {
+                ui.label("Chair ");
+                ui.text_edit_singleline(&mut self.sign_off_name);
+            });
+            let valid = !self.sign_off_name.trim().is_empty();
+            if ui.add_enabled(valid, egui::Button::new(format!("{} Sign off and lock", egui_phosphor::LOCK))).clicked() {
+                let name = std::mem::take(&mut self.sign_off_name);
+                self.log_sign_off(SignOffKind::Locked, name, String::new());
+            }
+        }
+
+        ui.separator();
+        ui.label("History");
+        for entry in self.sign_off.iter().rev() {
+            let text = match entry.kind {
+                SignOffKind::Locked => format!("{} locked by {}", entry.time, entry.name),
+                SignOffKind::Reopened => format!("{} re-opened by {}: {}", entry.time, entry.name, entry.reason),
+            };
+            ui.label(text).on_hover_text(format!("sha256 {}", entry.hash));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::{ Fault, FaultCategory };
+
+    #[test]
+    fn faults_change_the_hash() {
+        let mut judged = Judged::new();
+        let unfaulted = judged.content_hash();
+        judged.log_sign_off(SignOffKind::Locked, "Chair".to_owned(), String::new());
+        assert!(!judged.tampered());
+
+        judged.faults.push(Fault { time: 1.5, category: FaultCategory::BentLegs, deduction: 0.1, note: String::new() });
+        assert!(judged.tampered());
+        let faulted = judged.content_hash();
+        assert_ne!(faulted, unfaulted);
+
+        judged.faults[0].note = "knees".to_owned();
+        assert_ne!(judged.content_hash(), faulted);
+        judged.faults[0].note = String::new();
+        judged.faults[0].category = FaultCategory::Other;
+        assert_ne!(judged.content_hash(), faulted);
+
+        judged.faults.clear();
+        assert_eq!(judged.content_hash(), unfaulted);
+        assert!(!judged.tampered());
+    }
+
+    #[test]
+    fn video_evidence_changes_the_hash() {
+        let mut judged = Judged::new();
+        let without = judged.content_hash();
+        judged.video = "final.mp4".to_owned();
+        judged.video_points = vec![1.0, 2.0];
+        judged.log_sign_off(SignOffKind::Locked, "Chair".to_owned(), String::new());
+        let with = judged.content_hash();
+        assert_ne!(with, without);
+
+        judged.video_points[1] = 2.1;
+        assert!(judged.tampered());
+        judged.video_points[1] = 2.0;
+        judged.video = "other.mp4".to_owned();
+        assert!(judged.tampered());
+        judged.video = "final.mp4".to_owned();
+        assert!(!judged.tampered());
+    }
+}
@@ -0,0 +1,158 @@
+use std::{ io::Write, path::Path };
+use ffmpeg_sidecar::event::FfmpegEvent;
+
+use crate::decoder::{ ffmpeg, passthrough };
+use crate::sequence::Source;
+
+/// Summed RGB difference from the background above which a pixel belongs to the athlete.
+pub const STROBE_THRESHOLD: f32 = 60.0;
+
+pub struct StrobeSettings {
+    pub start: f32,
+    pub end: f32,
+    /// Frames per second the video is sampled at before taking every Nth frame.
+    pub fps: f32,
+    pub every: usize,
+    pub long_edge: Option<u32>,
+    pub threshold: f32,
+    /// Bytes of decoded frames held at once, the video's share of the memory budget.
+    pub memory: usize,
+}
+
+struct Frame {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+/// Every `every`th frame between `start` and `end`. When they don't fit in `memory`, every
+/// other one is dropped and the step doubled, so the frames still cover the whole skill.
+fn strobe_frames(source: &Source, settings: &StrobeSettings) -> Result<Vec<Frame>, String> {
+    let mut filters = vec![format!("fps=fps={}", settings.fps)];
+    if let Some(edge) = settings.long_edge {
+        filters.push(format!("scale=w='if(gte(iw,ih),{0},-1)':h='if(lt(iw,ih),{0},-1)'", edge));
+    }
+    filters.push(format!("select='not(mod(n\\,{}))'", settings.every.max(1)));
+    let mut frames: Vec<Frame> = vec![];
+    let mut used = 0;
+    // keep one in `kept` of the frames ffmpeg sends
+    let mut kept = 1;
+    let mut sent = 0;
+    let mut errors = vec![];
+    source
+        .input(ffmpeg().seek(settings.start.to_string()))
+        .duration((settings.end - settings.start).to_string())
+        .args(["-vf", &filters.join(",")])
+        .args(passthrough())
+        .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .spawn()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map_err(|e| e.to_string())?
+        .for_each(|event| {
+            match event {
+                FfmpegEvent::OutputFrame(frame) => {
+                    if sent % kept == 0 {
+                        used += frame.data.len();
+                        frames.push(Frame {
+                            width: frame.width,
+                            height: frame.height,
+                            data: frame.data,
+                        });
+                        if used > settings.memory && frames.len() > 1 {
+                            frames = std::mem::take(&mut frames).into_iter().step_by(2).collect();
+                            used = frames.iter().map(|f| f.data.len()).sum();
+                            kept *= 2;
+                        }
+                    }
+                    sent += 1;
+                }
+                FfmpegEvent::Error(e) => errors.push(e),
+                _ => {}
+            }
+        });
+    if frames.is_empty() {
+        return Err(match errors.is_empty() {
+            true => format!("No frames between {:.2}sec and {:.2}sec", settings.start, settings.end),
+            false => errors.join("\n"),
+        });
+    }
+    Ok(frames)
+}
+
+/// Median of every pixel over `frames`. The athlete only covers a pixel in a few of them,
+/// so what is left is the empty hall.
+fn background(frames: &[Frame]) -> Vec<u8> {
+    let mut values = Vec::with_capacity(frames.len());
+    (0..frames[0].data.len())
+        .map(|i| {
+            values.clear();
+            values.extend(frames.iter().map(|f| f.data[i]));
+            values.sort_unstable();
+            values[values.len() / 2]
+        })
+        .collect()
+}
+
+/// One image showing the athlete on every `every`th frame of the skill, over the background.
+pub fn strobe_composite(source: &Source, settings: &StrobeSettings) -> Result<(u32, u32, Vec<u8>), String> {
+    let frames = strobe_frames(source, settings)?;
+    if frames.len() < 3 {
+        return Err(
+            format!("Only {} frames, use a smaller step, or a lower resolution if they don't fit in memory", frames.len())
+        );
+    }
+    let (width, height) = (frames[0].width, frames[0].height);
+    let background = background(&frames);
+    let mut image = background.clone();
+    for frame in frames.iter() {
+        for ((pixel, out), bg) in frame.data
+            .chunks_exact(4)
+            .zip(image.chunks_exact_mut(4))
+            .zip(background.chunks_exact(4)) {
+            let difference = (0..3).map(|c| (pixel[c] as f32 - bg[c] as f32).abs()).sum::<f32>();
+            if difference > settings.threshold {
+                out.copy_from_slice(pixel);
+            }
+        }
+    }
+    Ok((width, height, image))
+}
+
+/// Encodes an RGBA image with ffmpeg.
+pub fn save_png(path: &Path, width: u32, height: u32, data: Vec<u8>) -> Result<(), String> {
+    let mut child = ffmpeg()
+        .format("rawvideo")
+        .args(["-pix_fmt", "rgba", "-s", &format!("{}x{}", width, height)])
+        .input("-")
+        .args(["-frames:v", "1"])
+        .overwrite()
+        .output(path.display().to_string())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let mut stdin = child.take_stdin().ok_or("ffmpeg has no input")?;
+    // write on another thread so ffmpeg never blocks on a full stderr pipe
+    let writer = std::thread::spawn(move || stdin.write_all(&data));
+    let mut errors = vec![];
+    child
+        .iter()
+        .map_err(|e| e.to_string())?
+        .for_each(|event| {
+            if let FfmpegEvent::Error(e) = event {
+                errors.push(e);
+            }
+        });
+    if let Ok(Err(e)) = writer.join() {
+        errors.push(e.to_string());
+    }
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(errors.join("\n")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn export_strobe(source: &Source, settings: &StrobeSettings, path: &Path) -> Result<(), String> {
+    let (width, height, image) = strobe_composite(source, settings)?;
+    save_png(path, width, height, image)
+}
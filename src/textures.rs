@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use macroquad::texture::Texture2D;
+
+use crate::decoder::DecodedFrame;
+
+struct CachedTexture {
+    texture: Texture2D,
+    bytes: usize,
+    last_used: u64,
+}
+
+/// Frames uploaded to the GPU, dropping the least recently shown once over `budget` bytes.
+pub struct TextureCache {
+    textures: HashMap<usize, CachedTexture>,
+    pub budget: usize,
+    used: usize,
+    clock: u64,
+}
+
+impl TextureCache {
+    pub fn new(budget: usize) -> TextureCache {
+        TextureCache {
+            textures: HashMap::new(),
+            budget,
+            used: 0,
+            clock: 0,
+        }
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.textures.contains_key(&index)
+    }
+
+    pub fn peek(&self, index: usize) -> Option<Texture2D> {
+        self.textures.get(&index).map(|t| t.texture)
+    }
+
+    /// The texture of frame `index`, marked as just used.
+    pub fn get(&mut self, index: usize) -> Option<Texture2D> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.textures.get_mut(&index).map(|t| {
+            t.last_used = clock;
+            t.texture
+        })
+    }
+
+    pub fn insert(&mut self, frame: &DecodedFrame) {
+        if self.contains(frame.index) {
+            return;
+        }
+        self.clock += 1;
+        self.used += frame.data.len();
+        self.textures.insert(frame.index, CachedTexture {
+            texture: Texture2D::from_rgba8(frame.width as u16, frame.height as u16, &frame.data),
+            bytes: frame.data.len(),
+            last_used: self.clock,
+        });
+        self.evict();
+    }
+
+    /// Deletes textures until the cache fits the budget, always keeping the newest one.
+    pub fn evict(&mut self) {
+        while self.used > self.budget && self.textures.len() > 1 {
+            let oldest = *self.textures
+                .iter()
+                .min_by_key(|(_, t)| t.last_used)
+                .unwrap().0;
+            if let Some(t) = self.textures.remove(&oldest) {
+                t.texture.delete();
+                self.used -= t.bytes;
+            }
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn clear(&mut self) {
+        for (_, t) in self.textures.drain() {
+            t.texture.delete();
+        }
+        self.used = 0;
+    }
+}
@@ -0,0 +1,212 @@
+/// Outer length of a competition trampoline frame, used as the default calibration.
+pub const FRAME_LENGTH: f32 = 5.05;
+
+/// The athlete's hips on one frame, as fractions of the frame size.
+#[derive(Debug, Clone, Copy, Savefile, PartialEq)]
+pub struct TrackPoint {
+    /// Decoded frame index points were kept by before version 8, which moved with the
+    /// decode sampling. Turned into `time` by `Tracking::frames_to_times`.
+    #[savefile_versions = "..7"]
+    #[savefile_default_val = "0"]
+    pub frame: usize,
+    /// Seconds into the video.
+    #[savefile_versions = "8.."]
+    pub time: f64,
+    pub position: [f32; 2],
+}
+
+/// A known length in the video, to turn pixels into metres.
+#[derive(Debug, Clone, Copy, Savefile, PartialEq)]
+pub struct Calibration {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    pub metres: f32,
+}
+
+#[derive(Debug, Clone, Default, Savefile, PartialEq)]
+pub struct Tracking {
+    /// Sorted by time.
+    pub points: Vec<TrackPoint>,
+    pub calibration: Option<Calibration>,
+    /// Points of tracks saved before version 8 only have their `frame`, until
+    /// `frames_to_times` has run.
+    #[savefile_versions = "8.."]
+    #[savefile_default_val = "true"]
+    pub by_frame: bool,
+}
+
+/// Peak height and travel of one element.
+#[derive(Debug, Clone, Copy)]
+pub struct FlightStats {
+    pub height: f32,
+    pub drift: f32,
+}
+
+impl Tracking {
+    /// Times the points of a track saved by frame index, with `time` of the frames they
+    /// were tracked on.
+    pub fn frames_to_times(&mut self, time: impl Fn(usize) -> f64) {
+        if !self.by_frame {
+            return;
+        }
+        for point in self.points.iter_mut() {
+            point.time = time(point.frame);
+            point.frame = 0;
+        }
+        self.by_frame = false;
+    }
+
+    /// Sets the hips at `time`, replacing any point less than `within` seconds away.
+    pub fn set(&mut self, time: f64, position: [f32; 2], within: f64) {
+        self.remove(time, within);
+        let i = self.points.partition_point(|p| p.time < time);
+        self.points.insert(i, TrackPoint { frame: 0, time, position });
+    }
+
+    /// Removes the points less than `within` seconds from `time`.
+    pub fn remove(&mut self, time: f64, within: f64) {
+        self.points.retain(|p| (p.time - time).abs() >= within);
+    }
+
+    /// Hip position at `time`, interpolated between the tracked points either side.
+    pub fn position(&self, time: f64) -> Option<[f32; 2]> {
+        let i = self.points.partition_point(|p| p.time < time);
+        let b = *self.points.get(i)?;
+        if b.time == time {
+            return Some(b.position);
+        }
+        if i == 0 {
+            return None;
+        }
+        let a = self.points[i - 1];
+        let t = ((time - a.time) / (b.time - a.time)) as f32;
+        Some([
+            a.position[0] + (b.position[0] - a.position[0]) * t,
+            a.position[1] + (b.position[1] - a.position[1]) * t,
+        ])
+    }
+
+    /// Metres per pixel of a `width` by `height` video.
+    pub fn scale(&self, width: f32, height: f32) -> Option<f32> {
+        let c = self.calibration?;
+        let pixels = (((c.end[0] - c.start[0]) * width).powi(2) + ((c.end[1] - c.start[1]) * height).powi(2)).sqrt();
+        match pixels > 0.0 {
+            true => Some(c.metres / pixels),
+            false => None,
+        }
+    }
+
+    /// Height above the take-off point and horizontal travel for the flight between two times.
+    pub fn flight(&self, start: f64, end: f64, width: f32, height: f32) -> Option<FlightStats> {
+        let scale = self.scale(width, height)?;
+        let first = self.position(start)?;
+        let last = self.position(end)?;
+        // the track is straight between points, so the top is at one of them
+        let top = self.points
+            .iter()
+            .filter(|p| p.time > start && p.time < end)
+            .map(|p| p.position[1])
+            .chain([first[1], last[1]])
+            .fold(first[1], f32::min);
+        Some(FlightStats {
+            height: (first[1] - top) * height * scale,
+            drift: (last[0] - first[0]) * width * scale,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(points: &[(f64, [f32; 2])]) -> Tracking {
+        let mut tracking = Tracking::default();
+        for (time, position) in points {
+            tracking.set(*time, *position, 0.01);
+        }
+        tracking
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn positions_between_points() {
+        let tracking = track(&[(2.0, [0.6, 0.2]), (1.0, [0.4, 0.6])]);
+        assert_eq!(tracking.points[0].time, 1.0);
+        assert_eq!(tracking.position(1.0), Some([0.4, 0.6]));
+        let middle = tracking.position(1.5).unwrap();
+        assert!(close(middle[0], 0.5) && close(middle[1], 0.4));
+        assert_eq!(tracking.position(0.5), None);
+        assert_eq!(tracking.position(2.5), None);
+    }
+
+    #[test]
+    fn points_on_the_same_frame_are_replaced() {
+        let mut tracking = track(&[(1.0, [0.4, 0.6])]);
+        tracking.set(1.005, [0.5, 0.5], 0.01);
+        assert_eq!(tracking.points.len(), 1);
+        assert_eq!(tracking.position(1.005), Some([0.5, 0.5]));
+        tracking.set(1.05, [0.5, 0.4], 0.01);
+        assert_eq!(tracking.points.len(), 2);
+        tracking.remove(1.001, 0.01);
+        assert_eq!(tracking.points.len(), 1);
+        assert_eq!(tracking.points[0].time, 1.05);
+    }
+
+    #[test]
+    fn height_and_drift_in_metres() {
+        let mut tracking = track(&[(1.0, [0.4, 0.8]), (1.5, [0.45, 0.2]), (2.0, [0.5, 0.8])]);
+        assert!(tracking.flight(1.0, 2.0, 1000.0, 500.0).is_none());
+        // 5 metres across the width of a 1000 pixel frame
+        tracking.calibration = Some(Calibration { start: [0.0, 0.5], end: [1.0, 0.5], metres: 5.0 });
+        assert!(close(tracking.scale(1000.0, 500.0).unwrap(), 0.005));
+        let stats = tracking.flight(1.0, 2.0, 1000.0, 500.0).unwrap();
+        assert!(close(stats.height, 0.6 * 500.0 * 0.005));
+        assert!(close(stats.drift, 0.1 * 1000.0 * 0.005));
+        // the top between tracked points is interpolated
+        let stats = tracking.flight(1.0, 1.25, 1000.0, 500.0).unwrap();
+        assert!(close(stats.height, 0.3 * 500.0 * 0.005));
+        assert!(tracking.flight(0.5, 2.0, 1000.0, 500.0).is_none());
+
+        tracking.calibration = Some(Calibration { start: [0.5, 0.5], end: [0.5, 0.5], metres: 5.0 });
+        assert!(tracking.scale(1000.0, 500.0).is_none());
+    }
+
+    /// `TrackPoint` and `Tracking` as they were saved up to version 7.
+    #[derive(Savefile)]
+    struct OldPoint {
+        frame: usize,
+        position: [f32; 2],
+    }
+
+    #[derive(Savefile)]
+    struct OldTracking {
+        points: Vec<OldPoint>,
+        calibration: Option<Calibration>,
+    }
+
+    #[test]
+    fn tracks_saved_by_frame_get_times() {
+        let old = OldTracking {
+            points: vec![OldPoint { frame: 48, position: [0.4, 0.6] }, OldPoint { frame: 96, position: [0.5, 0.5] }],
+            calibration: None,
+        };
+        let mut bytes = vec![];
+        savefile::save(&mut bytes, 7, &old).unwrap();
+        let mut tracking: Tracking = savefile::load(&mut &bytes[..], 8).unwrap();
+        assert!(tracking.by_frame);
+        tracking.frames_to_times(|frame| (frame as f64) / 48.0);
+        assert!(!tracking.by_frame);
+        assert_eq!(tracking.points.iter().map(|p| p.time).collect::<Vec<f64>>(), vec![1.0, 2.0]);
+        // only once
+        tracking.frames_to_times(|frame| (frame as f64) / 24.0);
+        assert_eq!(tracking.points[1].time, 2.0);
+
+        let mut bytes = vec![];
+        savefile::save(&mut bytes, 8, &tracking).unwrap();
+        let reloaded: Tracking = savefile::load(&mut &bytes[..], 8).unwrap();
+        assert_eq!(reloaded, tracking);
+    }
+}
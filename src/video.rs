@@ -7,137 +7,1684 @@
 )]
 
 
-use std::{ ops::RangeInclusive, time::UNIX_EPOCH };
-use egui::{plot::{ HLine, Plot, PlotPoint, Polygon, VLine }, PointerButton};
-use ffmpeg_sidecar::{ command::FfmpegCommand, event::{ FfmpegEvent, OutputVideoFrame } };
-use macroquad::texture::Texture2D;
+use std::{ collections::HashSet, ops::RangeInclusive, sync::atomic::{ AtomicU64, Ordering }, time::UNIX_EPOCH };
+use egui::{plot::{ HLine, Line, LineStyle, MarkerShape, Plot, PlotPoint, PlotPoints, Points, Polygon, Text, VLine }, PointerButton};
+use strum::IntoEnumIterator;
+use macroquad::prelude::*;
 use nfd2::Response;
 
+use crate::decoder::*;
+use crate::detect::*;
+use crate::clips::*;
+use crate::tracking::*;
+use crate::audio::*;
+use crate::textures::TextureCache;
+use crate::sequence::*;
+use crate::strobe::*;
+use crate::annotate::*;
+use crate::faults::*;
+use crate::watch::WatchSettings;
+use crate::{ flight_height, Judged, Routine };
+use macroquad::logging::error;
+
+/// The parts of a `Video` kept between runs, saved to `Data/videos`.
+#[derive(Debug, Clone, Savefile)]
+pub struct VideoSession {
+    pub id: String,
+    pub path: String,
+    pub start_from: f32,
+    pub points: Vec<f32>,
+    pub timestamps: bool,
+    pub interpolate: bool,
+    pub roi: Option<[f32; 4]>,
+    pub tof_total: f32,
+    pub skill_tof: [f32; 10],
+    #[savefile_versions = "2.."]
+    pub tracking: Tracking,
+    #[savefile_versions = "3.."]
+    #[savefile_default_fn = "default_long_edge"]
+    pub long_edge: Option<u32>,
+    #[savefile_versions = "3.."]
+    pub sampling: Sampling,
+    #[savefile_versions = "4.."]
+    #[savefile_default_fn = "default_image_fps"]
+    pub image_fps: f32,
+    #[savefile_versions = "5.."]
+    pub routine_id: String,
+    #[savefile_versions = "6.."]
+    pub annotations: Vec<Annotation>,
+    #[savefile_versions = "7.."]
+    pub faults: Vec<Fault>,
+}
+
+fn default_long_edge() -> Option<u32> {
+    Some(LONG_EDGE)
+}
+
+fn default_image_fps() -> f32 {
+    IMAGE_FPS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackMode {
+    Off,
+    Hips,
+    Calibrate,
+}
+
+/// Frames decoded per request, two seconds at the default rate.
+const DECODE_CHUNK: usize = 96;
+/// Playback speeds cycled by J and L.
+const SPEEDS: [f32; 5] = [0.1, 0.25, 0.5, 1.0, 2.0];
+
+/// Memory for decoded frames and textures until the app sets a budget.
+const DEFAULT_BUDGET: usize = 512 * 1024 * 1024;
+/// Frames ahead of the playhead uploaded to the GPU while playing.
+const PRELOAD_TEXTURES: usize = 8;
+
+/// Path, source, probe and timeline of a video that finished opening.
+type Opened = Result<(String, Source, VideoInfo, Timeline), String>;
+
+/// Last id handed out by `new_id`.
+static LAST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A millisecond timestamp, moved on past ids already in use so videos opened together,
+/// like several recordings from one folder scan, don't share windows or session files.
+fn new_id() -> String {
+    let now = UNIX_EPOCH.elapsed().unwrap().as_millis() as u64;
+    loop {
+        let last = LAST_ID.load(Ordering::SeqCst);
+        let id = now.max(last + 1);
+        if LAST_ID.compare_exchange(last, id, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            continue;
+        }
+        if !std::path::Path::new(&format!("Data/videos/{}.bin", id)).exists() {
+            return id.to_string();
+        }
+    }
+}
 
 pub struct Video {
+    /// A video file, or a folder or glob of numbered images.
     pub path: String,
+    source: Source,
+    /// Frame rate of image sequences, which don't have one of their own.
+    image_fps: f32,
     pub open: bool,
-    pub textures: Vec<(Texture2D, f32)>,
+    pub info: Option<VideoInfo>,
+    decoder: Option<Decoder>,
+    cache: FrameCache,
+    textures: TextureCache,
+    /// Frame whose texture is on screen.
+    shown: Option<usize>,
     pub current_frame: usize,
     pub show_video: bool,
     pub kill: bool,
     pub rect: [f32; 4],
     pub timestamps: bool,
-    pub drag_one: usize,
     pub points: Vec<f32>,
+    pub playing: bool,
+    reverse: bool,
+    speed: f32,
+    /// Playhead in seconds, between frames during slow playback.
+    play_time: f32,
+    loop_marks: Option<(usize, usize)>,
     add_points: bool,
     start_from: f32,
     id: String,
-    thread: Vec<std::thread::JoinHandle<Vec<OutputVideoFrame>>>,
+    thread: Vec<std::thread::JoinHandle<Opened>>,
+    pub timeline: Timeline,
+    long_edge: Option<u32>,
+    sampling: Sampling,
     interpolate: bool,
     pub full_size: bool,
     skill_tof: [f32;10],
+    tof_total: f32,
+    /// Region around the bed used for bounce detection, `[left, top, right, bottom]` fractions of the frame.
+    roi: Option<[f32; 4]>,
+    select_roi: bool,
+    roi_drag: Option<[f32; 2]>,
+    show_roi: bool,
+    pub suggestions: Vec<Suggestion>,
+    analysis: Option<std::thread::JoinHandle<Result<Vec<Suggestion>, String>>>,
+    /// Id of the judged routine the ToF will be sent to.
+    send_to: String,
+    /// Id of the routine whose skills the marked elements are.
+    routine_id: String,
+    confirm_send: bool,
+    /// Seconds kept either side of an exported clip.
+    clip_padding: f32,
+    clip_export: Option<std::thread::JoinHandle<Result<usize, String>>>,
+    clip_status: String,
+    /// Judged routine and routine ids whose routine file could not be loaded, so it isn't
+    /// read again every frame.
+    missing_routines: HashSet<(String, String)>,
+    /// Take-off and landing pair the strobe image is made of.
+    strobe_element: usize,
+    strobe_every: usize,
+    strobe_threshold: f32,
+    strobe_export: Option<std::thread::JoinHandle<Result<std::path::PathBuf, String>>>,
+    strobe_status: String,
+    pub tracking: Tracking,
+    pub annotations: Vec<Annotation>,
+    annotate_tool: Option<Tool>,
+    /// Points of the shape being drawn.
+    annotation_draft: Vec<[f32; 2]>,
+    /// Pointer over the frame, to preview the shape being drawn.
+    annotation_pointer: Option<[f32; 2]>,
+    annotation_export: Option<std::thread::JoinHandle<Result<usize, String>>>,
+    annotation_status: String,
+    pub faults: Vec<Fault>,
+    fault_category: FaultCategory,
+    fault_deduction: f32,
+    fault_note: String,
+    /// Judge whose marks the faults are imported as, when judged by five.
+    fault_judge: usize,
+    confirm_faults: bool,
+    track_mode: TrackMode,
+    /// Frames to move on after each tracked click.
+    track_step: usize,
+    calibration_start: Option<[f32; 2]>,
+    calibration_metres: f32,
+    show_track: bool,
+    /// Loudness of the audio track, see `audio_envelope`.
+    audio: Option<Vec<f32>>,
+    audio_thread: Option<std::thread::JoinHandle<Result<Vec<f32>, String>>>,
+    /// Suggest contacts from the audio once it is loaded.
+    audio_detect: bool,
+    /// Start detecting contacts as soon as the video has opened.
+    detect_on_open: bool,
+    load_job: Job,
+    /// Message and ffmpeg log of the last thing that went wrong.
+    error: Option<(String, String)>,
 }
 
 impl Video {
-    fn load_textures(
+    fn open_file(
         path2: Option<String>,
-        timestamps: bool,
-        start_from: f32,
-        frame_interpolation: bool
-    ) -> Result<Vec<OutputVideoFrame>, ffmpeg_sidecar::error::Error> {
-        let mut textures = Vec::new();
+        sampling: Sampling,
+        image_fps: f32,
+        job: Job
+    ) -> Opened {
         let path;
         if path2.is_none() {
             path = match nfd2::open_file_dialog(None, None) {
                 Ok(Response::Okay(file_path)) => { file_path.display().to_string() }
-                _ => { "".to_string() }
+                _ => {
+                    return Err(CANCELLED.to_owned());
+                }
             };
         } else {
             path = path2.unwrap();
         }
-        let filter = format!("scale=w='if(gte(iw,ih),720,-1)':h='if(lt(iw,ih),720,-1)',{}, drawtext=fontsize=50:fontcolor=GreenYellow:text='%{{e\\:t}}':x=(w-text_w):y=(h-text_h)", match frame_interpolation {
-                        true => format!("minterpolate=fps={}:mi_mode=mci", 48.0),
-                        false => format!("fps=fps={}", 48.0),
+        let source = Source::open(&path, image_fps)?;
+        let info = probe(&source, &job)?;
+        let timeline = timeline(&source, &info, sampling, &job)?;
+        Ok((path, source, info, timeline))
+    }
+
+    /// Reopens a saved session and starts loading its video, which opens at `start_from`.
+    pub fn from_session(session: VideoSession) -> Video {
+        let mut video = Video::new();
+        video.id = session.id;
+        video.start_from = session.start_from;
+        video.points = session.points;
+        video.timestamps = session.timestamps;
+        video.interpolate = session.interpolate;
+        video.roi = session.roi;
+        video.tof_total = session.tof_total;
+        video.skill_tof = session.skill_tof;
+        video.tracking = session.tracking;
+        // native frame times are only known once the video is read again
+        if let Sampling::Fixed(fps) = session.sampling {
+            video.tracking.frames_to_times(|frame| (frame as f64) / (fps as f64));
+        }
+        video.long_edge = session.long_edge;
+        video.sampling = session.sampling;
+        video.image_fps = session.image_fps;
+        video.routine_id = session.routine_id;
+        video.annotations = session.annotations;
+        video.faults = session.faults;
+        video.path = session.path;
+        video.reload();
+        video
+    }
+
+    /// Opens a recording found in the watched folder, linked and analysed as configured.
+    pub fn watched(path: String, settings: &WatchSettings) -> Video {
+        let mut video = Video::new();
+        video.routine_id = settings.routine_id.clone();
+        video.send_to = settings.judged_id.clone();
+        video.detect_on_open = settings.analyse;
+        video.start_loading(Some(path));
+        video
+    }
+
+    /// Opens the video again, after the decode settings changed.
+    fn reload(&mut self) {
+        let path = self.path.clone();
+        // a restored session has no timeline yet and keeps its saved position
+        if self.info.is_some() && self.timeline.len() > 0 {
+            self.start_from = self.time(self.current_frame);
+        }
+        self.info = None;
+        self.decoder = None;
+        self.delete_textures();
+        self.playing = false;
+        self.start_loading(Some(path));
+    }
+
+    /// Opens `path` on another thread, or asks for a file when there is none.
+    fn start_loading(&mut self, path: Option<String>) {
+        let sampling = self.sampling;
+        let image_fps = self.image_fps;
+        self.load_job = Job::default();
+        self.error = None;
+        let job = self.load_job.clone();
+        self.thread.push(std::thread::spawn(move || Self::open_file(path, sampling, image_fps, job)));
+    }
+
+    fn display_error(&mut self, ui: &mut egui::Ui) {
+        let (message, log) = match &self.error {
+            Some(error) => error,
+            None => {
+                return;
+            }
+        };
+        ui.colored_label(egui::Color32::RED, format!("{} {}", egui_phosphor::WARNING, message));
+        if !log.is_empty() {
+            ui.collapsing("ffmpeg log", |ui| {
+                ui.add(egui::Label::new(egui::RichText::new(log).monospace()).wrap(true));
+            });
+        }
+        if ui.small_button("Dismiss").clicked() {
+            self.error = None;
+        }
+        ui.separator();
+    }
+
+    fn display_decode_settings(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox
+            ::from_label("resolution")
+            .selected_text(match self.long_edge {
+                Some(edge) => format!("{}px", edge),
+                None => "source".to_owned(),
+            })
+            .show_ui(ui, |ui| {
+                for edge in [Some(480), Some(720), Some(1080), None] {
+                    ui.selectable_value(&mut self.long_edge, edge, match edge {
+                        Some(edge) => format!("{}px", edge),
+                        None => "source".to_owned(),
                     });
-        FfmpegCommand::new()
-            .duration("30")
-            .args(["-ss", start_from.to_string().as_str()])
-            .input(path)
-            
-            .args(match timestamps {
-                true =>
-                    [
-                        "-vf",
-                        &filter
-                    ],
-                false => ["-vf", "scale=w='if(gte(iw,ih),720,-1)':h='if(lt(iw,ih),720,-1)'"],
+                }
+            });
+        egui::ComboBox
+            ::from_label("frame rate")
+            .selected_text(match self.sampling {
+                Sampling::Fixed(fps) => format!("{}fps", fps),
+                Sampling::Native => "native".to_owned(),
             })
-            .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
-            // .rawvideo()
-            .spawn()?
-            .iter()?
-            .for_each(|event: FfmpegEvent| {
-                match event {
-                    FfmpegEvent::OutputFrame(frame) => {
-                        textures.push(frame);
+            .show_ui(ui, |ui| {
+                for fps in [24.0, 30.0, 48.0, 60.0, 120.0, 240.0] {
+                    ui.selectable_value(&mut self.sampling, Sampling::Fixed(fps), format!("{}fps", fps));
+                }
+                ui.selectable_value(&mut self.sampling, Sampling::Native, "native")
+                    .on_hover_text("every source frame at its own timestamp");
+            });
+        ui.horizontal(|ui| {
+            ui.label(format!("{} image sequence rate ", egui_phosphor::IMAGES));
+            ui.add(egui::DragValue::new(&mut self.image_fps).speed(1.0).clamp_range(1.0..=1000.0).suffix("fps"))
+                .on_hover_text("the rate the images were taken at");
+        });
+        ui.add_enabled(has_drawtext(), egui::Checkbox::new(&mut self.timestamps, format!("{} timestamps", egui_phosphor::WATCH)))
+            .on_disabled_hover_text("This ffmpeg has no drawtext filter");
+        ui.add_enabled(
+            self.sampling != Sampling::Native,
+            egui::Checkbox::new(&mut self.interpolate, format!("{} interpolate frames", egui_phosphor::INTERSECT_SQUARE))
+        );
+    }
+
+    pub fn time(&self, frame: usize) -> f32 {
+        self.timeline.time(frame)
+    }
+
+    /// The frame shown at `time` seconds.
+    pub fn frame_at(&self, time: f32) -> usize {
+        self.timeline.frame(time)
+    }
+
+    pub fn session(&self) -> VideoSession {
+        VideoSession {
+            id: self.id.clone(),
+            path: self.path.clone(),
+            start_from: self.start_from,
+            points: self.points.clone(),
+            timestamps: self.timestamps,
+            interpolate: self.interpolate,
+            roi: self.roi,
+            tof_total: self.tof_total,
+            skill_tof: self.skill_tof,
+            tracking: self.tracking.clone(),
+            long_edge: self.long_edge,
+            sampling: self.sampling,
+            image_fps: self.image_fps,
+            routine_id: self.routine_id.clone(),
+            annotations: self.annotations.clone(),
+            faults: self.faults.clone(),
+        }
+    }
+
+    pub fn save(&self) {
+        if self.path.is_empty() {
+            return;
+        }
+        match std::fs::create_dir_all("./Data/videos") {
+            Ok(_) => {}
+            Err(e) => {
+                error!("Error creating directory: {}", e);
+            }
+        }
+        match savefile::save_file(format!("Data/videos/{}.bin", self.id), 8, &self.session()) {
+            Ok(_) => {}
+            Err(e) => {
+                error!("Error saving file: {}", e);
+            }
+        }
+    }
+
+    pub fn seek(&mut self, frame: usize) {
+        self.current_frame = frame.min(self.frame_count().max(1) - 1);
+        self.play_time = self.time(self.current_frame);
+    }
+
+    pub fn step(&mut self, frames: i64) {
+        self.playing = false;
+        self.seek((self.current_frame as i64 + frames).max(0) as usize);
+    }
+
+    fn play(&mut self, reverse: bool) {
+        if self.playing && self.reverse == reverse {
+            self.speed = SPEEDS.into_iter().find(|s| *s > self.speed).unwrap_or(self.speed);
+        } else {
+            self.playing = true;
+            self.reverse = reverse;
+            self.speed = self.speed.min(1.0);
+        }
+    }
+
+    /// Moves the playhead by the time since the last frame, waiting for frames that are not decoded yet.
+    fn advance(&mut self) {
+        if !self.playing || self.info.is_none() || !self.cache.contains(self.current_frame) {
+            return;
+        }
+        let last = self.frame_count() - 1;
+        let (start, end) = match self.loop_marks {
+            Some((a, b)) => (a.min(last), b.min(last)),
+            None => (0, last),
+        };
+        let (start, end) = (self.time(start), self.time(end));
+        let step = get_frame_time() * self.speed;
+        self.play_time += match self.reverse {
+            true => -step,
+            false => step,
+        };
+        if self.play_time > end {
+            match self.loop_marks {
+                Some(_) => {
+                    self.play_time = start;
+                }
+                None => {
+                    self.play_time = end;
+                    self.playing = false;
+                }
+            }
+        }
+        if self.play_time < start {
+            match self.loop_marks {
+                Some(_) => {
+                    self.play_time = end;
+                }
+                None => {
+                    self.play_time = start;
+                    self.playing = false;
+                }
+            }
+        }
+        self.current_frame = self.frame_at(self.play_time);
+    }
+
+    fn display_transport(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.small_button(egui_phosphor::SKIP_BACK).on_hover_text("previous frame (Left)").clicked().then(|| {
+                self.step(-1);
+            });
+            let icon = match self.playing {
+                true => egui_phosphor::PAUSE,
+                false => egui_phosphor::PLAY,
+            };
+            ui.small_button(icon).on_hover_text("play/pause (Space)").clicked().then(|| {
+                self.playing = !self.playing;
+                self.reverse = false;
+            });
+            ui.small_button(egui_phosphor::SKIP_FORWARD).on_hover_text("next frame (Right)").clicked().then(|| {
+                self.step(1);
+            });
+            ui.add(
+                egui::Slider::new(&mut self.speed, 0.1..=2.0)
+                    .logarithmic(true)
+                    .fixed_decimals(2)
+                    .suffix("x")
+            );
+            if self.reverse && self.playing {
+                ui.label(egui_phosphor::REWIND);
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut looping = self.loop_marks.is_some();
+            if ui.checkbox(&mut looping, format!("{} loop", egui_phosphor::REPEAT)).changed() {
+                self.loop_marks = match looping {
+                    true => self.marks_around(self.current_frame),
+                    false => None,
+                };
+            }
+            if let Some((start, end)) = &mut self.loop_marks {
+                ui.small_button("in").on_hover_text("loop from the playhead").clicked().then(|| {
+                    *start = self.current_frame;
+                    *end = (*end).max(*start);
+                });
+                ui.small_button("out").on_hover_text("loop to the playhead").clicked().then(|| {
+                    *end = self.current_frame;
+                    *start = (*start).min(*end);
+                });
+                ui.label(format!("{:.2}sec - {:.2}sec", self.timeline.time(*start), self.timeline.time(*end)));
+            }
+        });
+    }
+
+    /// The two marked points either side of `frame`, or the whole video when there are none.
+    fn marks_around(&self, frame: usize) -> Option<(usize, usize)> {
+        let time = self.time(frame);
+        let start = self.points
+            .iter()
+            .rev()
+            .find(|p| **p <= time)
+            .map(|p| self.frame_at(*p))
+            .unwrap_or(0);
+        let end = self.points
+            .iter()
+            .find(|p| **p > time)
+            .map(|p| self.frame_at(*p))
+            .unwrap_or(self.frame_count() - 1);
+        Some((start, end))
+    }
+
+    fn handle_keys(&mut self, ui: &egui::Ui) {
+        if !ui.ui_contains_pointer() || ui.memory(|m| m.focus().is_some()) {
+            return;
+        }
+        ui.input(|i| {
+            if i.key_pressed(egui::Key::Space) {
+                self.playing = !self.playing;
+                self.reverse = false;
+            }
+            if i.key_pressed(egui::Key::ArrowLeft) {
+                self.step(-1);
+            }
+            if i.key_pressed(egui::Key::ArrowRight) {
+                self.step(1);
+            }
+            if i.key_pressed(egui::Key::J) {
+                self.play(true);
+            }
+            if i.key_pressed(egui::Key::K) {
+                self.playing = false;
+            }
+            if i.key_pressed(egui::Key::L) {
+                self.play(false);
+            }
+            if i.key_pressed(egui::Key::F) {
+                self.add_fault();
+            }
+        });
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether the frame under the playhead is decoded.
+    pub fn frame_ready(&self) -> bool {
+        self.cache.contains(self.current_frame)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        match self.info {
+            Some(_) => self.timeline.len().max(1),
+            None => 0,
+        }
+    }
+
+    /// Takes decoded frames from the decoder, asks for the ones around the playhead
+    /// and uploads the frame to show.
+    pub fn update_frames(&mut self) {
+        let frame_count = self.frame_count();
+        if let Some(decoder) = &mut self.decoder {
+            for frame in decoder.receive() {
+                self.cache.insert(frame, self.current_frame);
+            }
+            let errors = decoder.take_errors();
+            if !errors.is_empty() {
+                self.error = Some(("Error decoding frames".to_owned(), errors.join("\n")));
+            }
+            if
+                !self.cache.contains(self.current_frame) &&
+                !decoder.is_pending(self.current_frame) &&
+                !decoder.is_past_end(self.current_frame)
+            {
+                match self.playing && self.reverse {
+                    true => {
+                        let start = (self.current_frame + 1).saturating_sub(DECODE_CHUNK);
+                        decoder.request(start, self.current_frame + 1 - start);
+                    }
+                    false => decoder.request(self.current_frame, DECODE_CHUNK.min(frame_count - self.current_frame)),
+                }
+            } else if self.playing && !decoder.is_pending(self.current_frame) {
+                // prefetch the next chunk before playback reaches it
+                let ahead = (1..DECODE_CHUNK / 2)
+                    .map(|i| match self.reverse {
+                        true => self.current_frame.saturating_sub(i),
+                        false => (self.current_frame + i).min(frame_count - 1),
+                    })
+                    .find(|i| !self.cache.contains(*i) && !decoder.is_pending(*i) && !decoder.is_past_end(*i));
+                if let Some(ahead) = ahead {
+                    match self.reverse {
+                        true => {
+                            let start = (ahead + 1).saturating_sub(DECODE_CHUNK);
+                            decoder.request(start, ahead + 1 - start);
+                        }
+                        false => decoder.request(ahead, DECODE_CHUNK.min(frame_count - ahead)),
                     }
-                    FfmpegEvent::Progress(progress) => {
-                        eprintln!("Current speed: {}x", progress.speed);
+                }
+            }
+        }
+        let shown = match self.cache.get(self.current_frame) {
+            Some(frame) => Some(frame),
+            None => self.cache.nearest(self.current_frame),
+        };
+        if let Some(frame) = shown {
+            self.textures.insert(frame);
+            self.textures.get(frame.index);
+            self.shown = Some(frame.index);
+        }
+        if self.playing {
+            // a couple of uploads per frame keeps playback from stalling on them
+            let mut uploads = 2;
+            for i in 1..=PRELOAD_TEXTURES {
+                let index = match self.reverse {
+                    true => self.current_frame.saturating_sub(i),
+                    false => self.current_frame + i,
+                };
+                if uploads == 0 {
+                    break;
+                }
+                if let Some(frame) = self.cache.get(index) {
+                    if !self.textures.contains(index) {
+                        self.textures.insert(frame);
+                        uploads -= 1;
                     }
-                    FfmpegEvent::Log(_level, msg) => {
-                        eprintln!("[ffmpeg] {}", msg);
+                }
+            }
+        }
+    }
+
+    /// Splits `bytes` between decoded frames in RAM and textures on the GPU.
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        self.cache.budget = bytes / 3 * 2;
+        self.textures.budget = bytes / 3;
+        self.cache.evict(self.current_frame);
+        self.textures.evict();
+    }
+
+    /// Draws the current frame over the space reserved for it in the window.
+    pub fn draw(&self, zoom: f32) {
+        let texture = match self.texture() {
+            Some(texture) => texture,
+            None => {
+                return;
+            }
+        };
+        let [x, y, w, h] = self.rect.map(|v| v * zoom);
+        draw_texture_ex(texture, x, y, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(w, h)),
+            ..Default::default()
+        });
+        if let Some(roi) = self.roi {
+            if self.show_roi || self.select_roi {
+                draw_rectangle_lines(
+                    x + roi[0] * w,
+                    y + roi[1] * h,
+                    (roi[2] - roi[0]) * w,
+                    (roi[3] - roi[1]) * h,
+                    2.0,
+                    YELLOW
+                );
+            }
+        }
+        let to_screen = |p: [f32; 2]| vec2(x + p[0] * w, y + p[1] * h);
+        let color = Color::from_rgba(ANNOTATION_COLOR[0], ANNOTATION_COLOR[1], ANNOTATION_COLOR[2], ANNOTATION_COLOR[3]);
+        let (width, height) = match self.info {
+            Some(info) => (info.width as f32, info.height as f32),
+            None => (w, h),
+        };
+        let mut shapes = self.frame_annotations().map(|a| a.shape.clone()).collect::<Vec<Shape>>();
+        shapes.extend(self.draft_shape());
+        for shape in shapes.iter() {
+            for (a, b) in shape.segments(w, h) {
+                let (a, b) = (to_screen(a), to_screen(b));
+                draw_line(a.x, a.y, b.x, b.y, 2.0, color);
+            }
+            if let Shape::Angle(a, vertex, b) = shape {
+                let p = to_screen(*vertex);
+                draw_text(&format!("{:.1}°", angle(*a, *vertex, *b, width, height)), p.x + 8.0, p.y - 8.0, 24.0 * zoom, color);
+            }
+        }
+        if !self.show_track {
+            return;
+        }
+        for pair in self.tracking.points.windows(2) {
+            let (a, b) = (to_screen(pair[0].position), to_screen(pair[1].position));
+            draw_line(a.x, a.y, b.x, b.y, 2.0, LIME);
+        }
+        for p in self.tracking.points.iter() {
+            let p = to_screen(p.position);
+            draw_circle_lines(p.x, p.y, 4.0, 1.0, LIME);
+        }
+        if let Some(p) = self.tracking.position(self.time(self.current_frame) as f64) {
+            let p = to_screen(p);
+            draw_circle(p.x, p.y, 5.0, RED);
+        }
+        if let Some(c) = self.tracking.calibration {
+            let (a, b) = (to_screen(c.start), to_screen(c.end));
+            draw_line(a.x, a.y, b.x, b.y, 2.0, SKYBLUE);
+        }
+        if let Some(start) = self.calibration_start {
+            let p = to_screen(start);
+            draw_circle(p.x, p.y, 4.0, SKYBLUE);
+        }
+    }
+
+    fn display_tracking(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.track_mode, TrackMode::Off, "off");
+            ui.selectable_value(&mut self.track_mode, TrackMode::Hips, format!("{} hips", egui_phosphor::CROSSHAIR));
+            if ui.selectable_value(&mut self.track_mode, TrackMode::Calibrate, format!("{} calibrate", egui_phosphor::RULER)).clicked() {
+                self.calibration_start = None;
+            }
+            ui.checkbox(&mut self.show_track, "show");
+        });
+        match self.track_mode {
+            TrackMode::Off => {}
+            TrackMode::Hips => {
+                ui.label("Click the hips on the frame, right click to remove");
+                ui.horizontal(|ui| {
+                    ui.label("Then skip ");
+                    ui.add(egui::DragValue::new(&mut self.track_step).clamp_range(0..=48).suffix(" frames"));
+                });
+            }
+            TrackMode::Calibrate => {
+                ui.label(match self.calibration_start {
+                    None => "Click one end of the known length",
+                    Some(_) => "Click the other end",
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Length ");
+                    ui.add(egui::DragValue::new(&mut self.calibration_metres).speed(0.01).clamp_range(0.1..=50.0).suffix("m"));
+                });
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.small_button("Clear track").clicked().then(|| {
+                self.tracking.points.clear();
+            });
+            ui.small_button("Clear calibration").clicked().then(|| {
+                self.tracking.calibration = None;
+            });
+        });
+        let info = match self.info {
+            Some(info) => info,
+            None => {
+                return;
+            }
+        };
+        if self.tracking.calibration.is_none() {
+            ui.label("Calibrate to measure height and drift");
+            return;
+        }
+        for (i, pair) in self.points.chunks_exact(2).enumerate() {
+            match self.tracking.flight(pair[0] as f64, pair[1] as f64, info.width as f32, info.height as f32) {
+                Some(stats) => ui.label(format!("{}.) height {:.2}m drift {:+.2}m", i + 1, stats.height, stats.drift)),
+                None => ui.label(format!("{}.) not tracked", i + 1)),
+            };
+        }
+    }
+
+    /// Handles a click on the frame while tracking or calibrating.
+    fn track_click(&mut self, response: &egui::Response) {
+        let pointer = match response.interact_pointer_pos() {
+            Some(pos) => [
+                ((pos.x - response.rect.left()) / response.rect.width()).clamp(0.0, 1.0),
+                ((pos.y - response.rect.top()) / response.rect.height()).clamp(0.0, 1.0),
+            ],
+            None => {
+                return;
+            }
+        };
+        match self.track_mode {
+            TrackMode::Off => {}
+            TrackMode::Hips => {
+                // a point belongs to the frame it was clicked on, whatever the frame rate
+                let time = self.time(self.current_frame) as f64;
+                let half_frame = 0.5 / (self.timeline.fps() as f64);
+                if response.clicked() {
+                    self.tracking.set(time, pointer, half_frame);
+                    self.step(self.track_step as i64);
+                }
+                if response.secondary_clicked() {
+                    self.tracking.remove(time, half_frame);
+                }
+            }
+            TrackMode::Calibrate => {
+                if response.clicked() {
+                    match self.calibration_start.take() {
+                        None => {
+                            self.calibration_start = Some(pointer);
+                        }
+                        Some(start) => {
+                            self.tracking.calibration = Some(Calibration {
+                                start,
+                                end: pointer,
+                                metres: self.calibration_metres,
+                            });
+                            self.track_mode = TrackMode::Off;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Annotations drawn on the frame being shown.
+    fn frame_annotations(&self) -> impl Iterator<Item = &Annotation> {
+        self.annotations.iter().filter(|a| self.frame_at(a.time) == self.current_frame)
+    }
+
+    /// The shape being drawn, finished at the pointer.
+    fn draft_shape(&self) -> Option<Shape> {
+        let d = &self.annotation_draft;
+        let last = self.annotation_pointer.or(d.last().copied())?;
+        match (self.annotate_tool?, d.len()) {
+            (_, 0) => None,
+            (Tool::Freehand, _) => Some(Shape::Freehand(d.clone())),
+            (Tool::Circle, _) => Some(Shape::Circle { centre: d[0], edge: last }),
+            (Tool::Angle, 2) => Some(Shape::Angle(d[0], d[1], last)),
+            _ => Some(Shape::Line(d[0], last)),
+        }
+    }
+
+    /// Handles the pointer on the frame while annotating.
+    fn annotate_input(&mut self, response: &egui::Response) {
+        let to_frame = |pos: egui::Pos2| [
+            ((pos.x - response.rect.left()) / response.rect.width()).clamp(0.0, 1.0),
+            ((pos.y - response.rect.top()) / response.rect.height()).clamp(0.0, 1.0),
+        ];
+        self.annotation_pointer = response.hover_pos().map(to_frame);
+        let (tool, pointer) = match (self.annotate_tool, response.interact_pointer_pos()) {
+            (Some(tool), Some(pos)) => (tool, to_frame(pos)),
+            _ => {
+                return;
+            }
+        };
+        if response.secondary_clicked() {
+            self.annotation_draft.clear();
+            return;
+        }
+        // the drawing belongs to the frame it was drawn on
+        self.playing = false;
+        let time = self.time(self.current_frame);
+        if tool == Tool::Angle {
+            if response.clicked() {
+                self.annotation_draft.push(pointer);
+                if self.annotation_draft.len() == 3 {
+                    let d = std::mem::take(&mut self.annotation_draft);
+                    self.annotations.push(Annotation { time, shape: Shape::Angle(d[0], d[1], d[2]) });
+                }
+            }
+            return;
+        }
+        if response.drag_started() {
+            self.annotation_draft = vec![pointer];
+        } else if response.dragged() && !self.annotation_draft.is_empty() {
+            if tool != Tool::Freehand {
+                self.annotation_draft.truncate(1);
+            }
+            self.annotation_draft.push(pointer);
+        }
+        if response.drag_released() && self.annotation_draft.len() >= 2 {
+            let d = std::mem::take(&mut self.annotation_draft);
+            let shape = match tool {
+                Tool::Line => Shape::Line(d[0], d[d.len() - 1]),
+                Tool::Circle => Shape::Circle { centre: d[0], edge: d[d.len() - 1] },
+                _ => Shape::Freehand(d),
+            };
+            self.annotations.push(Annotation { time, shape });
+        }
+    }
+
+    fn display_annotations(&mut self, ui: &mut egui::Ui) {
+        if let Some(export) = &self.annotation_export {
+            if export.is_finished() {
+                self.annotation_status = match self.annotation_export.take().unwrap().join() {
+                    Ok(Ok(count)) => format!("Exported {} frames", count),
+                    Ok(Err(e)) => {
+                        self.error = Some(("Error exporting annotated frames".to_owned(), e));
+                        String::new()
                     }
-                    _ => {}
+                    Err(_) => "Export failed".to_owned(),
+                };
+            }
+        }
+        let tool = self.annotate_tool;
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.annotate_tool, None, "off");
+            ui.selectable_value(&mut self.annotate_tool, Some(Tool::Line), format!("{} line", egui_phosphor::LINE_SEGMENT));
+            ui.selectable_value(&mut self.annotate_tool, Some(Tool::Angle), format!("{} angle", egui_phosphor::COMPASS_TOOL));
+            ui.selectable_value(&mut self.annotate_tool, Some(Tool::Circle), format!("{} circle", egui_phosphor::CIRCLE));
+            ui.selectable_value(&mut self.annotate_tool, Some(Tool::Freehand), format!("{} freehand", egui_phosphor::SCRIBBLE_LOOP));
+        });
+        if self.annotate_tool != tool {
+            self.annotation_draft.clear();
+        }
+        match self.annotate_tool {
+            Some(Tool::Angle) => {
+                ui.label("Click one end, the joint, then the other end. Right click to cancel");
+            }
+            Some(_) => {
+                ui.label("Drag on the frame. Right click to cancel");
+            }
+            None => {}
+        }
+        let (width, height) = match self.info {
+            Some(info) => (info.width as f32, info.height as f32),
+            None => {
+                return;
+            }
+        };
+
+        let current = self.time(self.current_frame);
+        let mut remove = None;
+        for (i, a) in self.annotations.iter().enumerate() {
+            if self.frame_at(a.time) != self.current_frame {
+                continue;
+            }
+            ui.horizontal(|ui| {
+                match &a.shape {
+                    Shape::Angle(..) => ui.strong(a.shape.describe(width, height)),
+                    _ => ui.label(a.shape.describe(width, height)),
+                };
+                if ui.small_button(egui_phosphor::X).on_hover_text("delete").clicked() {
+                    remove = Some(i);
                 }
             });
+        }
+        if let Some(i) = remove {
+            self.annotations.remove(i);
+        }
 
-        println!("Finished {} frames", textures.len());
-        Ok(textures)
+        let mut times = self.annotations
+            .iter()
+            .map(|a| self.time(self.frame_at(a.time)))
+            .collect::<Vec<f32>>();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        times.dedup();
+        if times.is_empty() {
+            return;
+        }
+        ui.separator();
+        ui.label("Annotated frames");
+        let mut seek = None;
+        ui.horizontal_wrapped(|ui| {
+            for time in times.iter() {
+                if ui.selectable_label(*time == current, format!("{:.2}sec", time)).clicked() {
+                    seek = Some(self.frame_at(*time));
+                }
+            }
+        });
+        if let Some(frame) = seek {
+            self.playing = false;
+            self.seek(frame);
+        }
+        if self.annotation_export.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Exporting...");
+            });
+            return;
+        }
+        let mut export = None;
+        ui.horizontal(|ui| {
+            ui.add_enabled(times.contains(&current), egui::Button::new(format!("{} This frame", egui_phosphor::IMAGE)))
+                .clicked()
+                .then(|| {
+                    export = Some(vec![current]);
+                });
+            ui.button(format!("{} All annotated frames", egui_phosphor::IMAGES))
+                .clicked()
+                .then(|| {
+                    export = Some(times.clone());
+                });
+        });
+        if let Some(times) = export {
+            if let Ok(Response::Okay(folder)) = nfd2::open_pick_folder(None) {
+                let frames = times
+                    .into_iter()
+                    .map(|time| {
+                        let shapes = self.annotations
+                            .iter()
+                            .filter(|a| self.time(self.frame_at(a.time)) == time)
+                            .map(|a| a.shape.clone())
+                            .collect::<Vec<Shape>>();
+                        (time, shapes)
+                    })
+                    .collect::<Vec<_>>();
+                let stem = std::path::Path::new(&self.path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or("frame".to_owned());
+                let source = self.source.clone();
+                let long_edge = self.long_edge;
+                self.annotation_status = String::new();
+                self.annotation_export = Some(
+                    std::thread::spawn(move || {
+                        for (time, shapes) in frames.iter() {
+                            let path = folder.join(format!("{} {:.2}sec.png", file_name(&stem), time));
+                            export_annotated(&source, *time, shapes, (width, height), long_edge, &path)?;
+                        }
+                        Ok(frames.len())
+                    })
+                );
+            }
+        }
+        if !self.annotation_status.is_empty() {
+            ui.label(&self.annotation_status);
+        }
     }
 
-    pub fn display(&mut self, egui_ctx: &egui::Context) {
+    /// Marks a fault with the chosen category and deduction on the frame being shown.
+    fn add_fault(&mut self) {
+        let time = self.time(self.current_frame);
+        self.faults.push(Fault {
+            time,
+            category: self.fault_category,
+            deduction: self.fault_deduction,
+            note: std::mem::take(&mut self.fault_note),
+        });
+        self.faults.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+    }
+
+    fn display_faults(&mut self, ui: &mut egui::Ui, judged: &mut [Judged], routines: &[Routine]) {
+        egui::ComboBox
+            ::from_label("category")
+            .selected_text(self.fault_category.name())
+            .show_ui(ui, |ui| {
+                for category in FaultCategory::iter() {
+                    ui.selectable_value(&mut self.fault_category, category, category.name());
+                }
+            });
+        ui.horizontal(|ui| {
+            for deduction in [0.1, 0.2, 0.3, 0.4, 0.5] {
+                ui.selectable_value(&mut self.fault_deduction, deduction, format!("-{:.1}", deduction));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Note ");
+            ui.text_edit_singleline(&mut self.fault_note);
+        });
+        if ui.button(format!("{} Mark fault at {:.2}sec", egui_phosphor::FLAG, self.time(self.current_frame)))
+            .on_hover_text("or press F")
+            .clicked()
+        {
+            self.add_fault();
+        }
+
+        let (names, _) = self.linked_names(judged, routines);
+        let mut remove = None;
+        let mut seek = None;
+        for (i, fault) in self.faults.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button(egui_phosphor::CROSSHAIR).on_hover_text("go to").clicked() {
+                    seek = Some(self.frame_at(fault.time));
+                }
+                let element = match element_at(&self.points, fault.time) {
+                    Some(k) => match names.get(k) {
+                        Some(name) => format!("{}.) {}", k + 1, name),
+                        None => format!("{}.)", k + 1),
+                    },
+                    None => "not in a flight".to_owned(),
+                };
+                ui.label(format!("{:.2}sec {} -{:.1} {}", fault.time, fault.category.name(), fault.deduction, element));
+                if !fault.note.is_empty() {
+                    ui.weak(&fault.note);
+                }
+                if ui.small_button(egui_phosphor::X).on_hover_text("delete").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.faults.remove(i);
+        }
+        if let Some(frame) = seek {
+            self.playing = false;
+            self.seek(frame);
+        }
+        if self.faults.is_empty() {
+            return;
+        }
+
+        ui.separator();
+        let target = match judged.iter_mut().find(|j| j.video == self.path || j.id == self.send_to) {
+            Some(j) => j,
+            None => {
+                ui.label("Send the ToF to a judged routine to import the faults as execution marks");
+                return;
+            }
+        };
+        if target.locked() {
+            ui.colored_label(egui::Color32::RED, format!("{} Locked by sign-off", egui_phosphor::LOCK));
+            return;
+        }
+        // faults are placed on elements by the flights of the video they were marked on
+        if !target.video.is_empty() && target.video != self.path {
+            ui.label(format!("{} was judged from {}, send this video's ToF to it first", target.athlete, target.video));
+            return;
+        }
+        if target.five_judges {
+            egui::ComboBox
+                ::from_label("judge")
+                .selected_text(format!("J{}", self.fault_judge + 1))
+                .show_ui(ui, |ui| {
+                    for k in 0..5 {
+                        ui.selectable_value(&mut self.fault_judge, k, format!("J{}", k + 1));
+                    }
+                });
+        }
+        let marks = fault_marks(&self.faults, &self.points);
+        let current = match target.five_judges {
+            true => target.execution_5[self.fault_judge],
+            false => target.execution_1,
+        };
+        egui::Grid::new("fault preview").striped(true).show(ui, |ui| {
+            ui.label("");
+            ui.label("Current");
+            ui.label("Video");
+            ui.end_row();
+            for i in 0..10 {
+                ui.label(format!("{}.)", i + 1));
+                ui.label(format!("{:.1}", current[i]));
+                ui.label(format!("{:.1}", marks[i]));
+                ui.end_row();
+            }
+        });
+
+        if self.confirm_faults {
+            ui.horizontal(|ui| {
+                ui.label("Overwrite the execution marks?");
+                if ui.small_button(format!("{} Confirm", egui_phosphor::CHECK)).clicked() {
+                    match target.five_judges {
+                        true => {
+                            target.execution_5[self.fault_judge] = marks;
+                        }
+                        false => {
+                            target.execution_1 = marks;
+                        }
+                    }
+                    target.faults = self.faults.clone();
+                    target.video = self.path.clone();
+                    target.video_points = self.points.clone();
+                    self.confirm_faults = false;
+                }
+                if ui.small_button("Cancel").clicked() {
+                    self.confirm_faults = false;
+                }
+            });
+        } else if ui.button(format!("{} Import as execution marks", egui_phosphor::DOWNLOAD_SIMPLE)).clicked() {
+            self.confirm_faults = true;
+        }
+    }
+
+    fn update_audio(&mut self) {
+        if let Some(thread) = &self.audio_thread {
+            if thread.is_finished() {
+                match self.audio_thread.take().unwrap().join() {
+                    Ok(Ok(envelope)) => {
+                        if self.audio_detect {
+                            self.suggestions = detect_audio_contacts(&envelope, &self.timeline);
+                            self.report_silence();
+                        }
+                        self.audio = Some(envelope);
+                    }
+                    Ok(Err(e)) if self.audio_detect => {
+                        self.error = Some(
+                            ("Could not read the audio, select the bed region and use Detect bounces instead".to_owned(), e)
+                        );
+                    }
+                    Ok(Err(e)) => {
+                        self.error = Some(("Error reading audio".to_owned(), e));
+                    }
+                    Err(_) => {}
+                }
+                self.audio_detect = false;
+            }
+        }
+    }
+
+    /// Points to motion detection when the audio gave nothing, as silent or missing
+    /// audio would otherwise just leave no suggestions.
+    fn report_silence(&mut self) {
+        if self.suggestions.is_empty() {
+            self.error = Some((
+                "No bounces heard in the audio, select the bed region and use Detect bounces instead".to_owned(),
+                String::new(),
+            ));
+        }
+    }
+
+    fn load_audio(&mut self, detect: bool) {
+        if self.audio_thread.is_some() {
+            return;
+        }
+        let source = self.source.clone();
+        self.audio_detect = detect;
+        self.audio_thread = Some(std::thread::spawn(move || audio_envelope(&source)));
+    }
+
+    fn display_detection(&mut self, ui: &mut egui::Ui) {
+        if let Some(analysis) = &self.analysis {
+            if analysis.is_finished() {
+                match self.analysis.take().unwrap().join() {
+                    Ok(Ok(suggestions)) => {
+                        self.suggestions = suggestions;
+                    }
+                    Ok(Err(e)) => {
+                        self.error = Some(("Error detecting bounces".to_owned(), e));
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+        ui.horizontal(|ui| {
+            if ui.selectable_label(self.select_roi, format!("{} select bed region", egui_phosphor::SELECTION)).clicked() {
+                self.select_roi = !self.select_roi;
+            }
+            ui.checkbox(&mut self.show_roi, "show region");
+        });
+        if self.analysis.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Analysing...");
+            });
+        } else if self.audio_thread.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Reading audio...");
+            });
+        } else if
+            ui
+                .button(format!("{} Detect from audio", egui_phosphor::WAVEFORM))
+                .on_hover_text(
+                    "Listens for the impact of every landing and the bed letting go at take-off. \
+                    Steady noise is fine, but claps or other beds close by will add false contacts."
+                )
+                .clicked()
+        {
+            match &self.audio {
+                Some(envelope) => {
+                    self.suggestions = detect_audio_contacts(envelope, &self.timeline);
+                    self.report_silence();
+                }
+                None => self.load_audio(true),
+            }
+        } else if
+            ui
+                .add_enabled(self.roi.is_some(), egui::Button::new(format!("{} Detect bounces", egui_phosphor::MAGIC_WAND)))
+                .on_disabled_hover_text("Select the bed region first")
+                .clicked()
+        {
+            let source = self.source.clone();
+            let roi = self.roi.unwrap();
+            let timeline = self.timeline.clone();
+            self.analysis = Some(
+                std::thread::spawn(move || {
+                    let fps = timeline.fps();
+                    let motion = motion_profile(&source, roi, fps)?;
+                    Ok(snap_to_frames(detect_contacts(&motion, fps), &timeline))
+                })
+            );
+        }
+        if self.suggestions.is_empty() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            if ui.small_button("Accept all").clicked() {
+                // pairs that would overlap a marked flight are left to sort out by hand
+                for pair in std::mem::take(&mut self.suggestions).chunks_exact(2) {
+                    match fits_flight(&self.points, pair[0].time, pair[1].time) {
+                        true => self.accept_flight(pair[0].time, pair[1].time),
+                        false => self.suggestions.extend_from_slice(pair),
+                    }
+                }
+            }
+            if ui.small_button("Reject all").clicked() {
+                self.suggestions.clear();
+            }
+        });
+        let mut remove = None;
+        let mut accept = None;
+        let mut seek = None;
+        // suggestions come as take-off and landing pairs, accepted together so the
+        // take-offs and landings in `points` stay in step
+        for (i, pair) in self.suggestions.chunks_exact_mut(2).enumerate() {
+            let fits = fits_flight(&self.points, pair[0].time, pair[1].time);
+            for (suggestion, name) in pair.iter_mut().zip(["take-off", "landing"]) {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} {:.3}sec {:.0}%", name, suggestion.time, suggestion.confidence * 100.0));
+                    let frame = self.timeline.frame(suggestion.time);
+                    if ui.small_button(egui_phosphor::CARET_LEFT).on_hover_text("one frame earlier").clicked() {
+                        suggestion.time = self.timeline.time(frame.saturating_sub(1));
+                        seek = Some(frame.saturating_sub(1));
+                    }
+                    if ui.small_button(egui_phosphor::CROSSHAIR).on_hover_text("go to").clicked() {
+                        seek = Some(frame);
+                    }
+                    if ui.small_button(egui_phosphor::CARET_RIGHT).on_hover_text("one frame later").clicked() {
+                        suggestion.time = self.timeline.time(frame + 1);
+                        seek = Some(frame + 1);
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                if
+                    ui
+                        .add_enabled(fits, egui::Button::new(egui_phosphor::CHECK).small())
+                        .on_hover_text("accept flight")
+                        .on_disabled_hover_text("Overlaps a marked flight")
+                        .clicked()
+                {
+                    accept = Some((pair[0].time, pair[1].time));
+                    remove = Some(i);
+                }
+                if ui.small_button(egui_phosphor::X).on_hover_text("reject flight").clicked() {
+                    remove = Some(i);
+                }
+            });
+            ui.separator();
+        }
+        if let Some((take_off, landing)) = accept {
+            self.accept_flight(take_off, landing);
+        }
+        if let Some(i) = remove {
+            self.suggestions.drain(i * 2..i * 2 + 2);
+        }
+        if let Some(frame) = seek {
+            self.seek(frame);
+        }
+    }
+
+    fn accept_flight(&mut self, take_off: f32, landing: f32) {
+        self.points.extend([take_off, landing]);
+        self.points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    }
+
+    pub fn texture(&self) -> Option<Texture2D> {
+        self.shown.and_then(|i| self.textures.peek(i))
+    }
+
+    pub fn delete_textures(&mut self) {
+        self.textures.clear();
+        self.shown = None;
+        self.cache.clear();
+    }
+
+    fn display_send(&mut self, ui: &mut egui::Ui, judged: &mut [Judged]) {
+        // the judged total only counts the ten elements that are sent
+        let tof_total = self.skill_tof.iter().sum::<f32>();
+        fn name(judged: &Judged) -> String {
+            format!(
+                "{} {} {}",
+                judged.athlete,
+                match &judged.routine {
+                    Some(routine) => &routine.name,
+                    None => "",
+                },
+                judged.date_of_creation
+            )
+        }
+        egui::ComboBox::from_label("Judged routine")
+            .selected_text(match judged.iter().find(|j| j.id == self.send_to) {
+                Some(j) => name(j),
+                None => "None".to_owned(),
+            })
+            .show_ui(ui, |ui| {
+                for j in judged.iter() {
+                    if ui.selectable_value(&mut self.send_to, j.id.clone(), name(j)).clicked() {
+                        self.confirm_send = false;
+                    }
+                }
+            });
+        let target = match judged.iter_mut().find(|j| j.id == self.send_to) {
+            Some(j) => j,
+            None => {
+                return;
+            }
+        };
+        if target.locked() {
+            ui.colored_label(egui::Color32::RED, format!("{} Locked by sign-off", egui_phosphor::LOCK));
+            return;
+        }
+
+        egui::Grid::new("send preview").striped(true).show(ui, |ui| {
+            ui.label("");
+            ui.label("Current");
+            ui.label("Video");
+            ui.end_row();
+            for i in 0..10 {
+                ui.label(format!("{}.)", i + 1));
+                ui.label(format!("{:.2}", target.tof[i]));
+                ui.label(format!("{:.2}", self.skill_tof[i]));
+                ui.end_row();
+            }
+            ui.label("Total");
+            ui.label(format!("{:.2}", target.tof_total));
+            ui.label(format!("{:.2}", tof_total));
+            ui.end_row();
+        });
+        if !target.video.is_empty() && target.video != self.path {
+            ui.label(format!("Replaces ToF from {}", target.video));
+        }
+
+        if self.confirm_send {
+            ui.horizontal(|ui| {
+                ui.label("Overwrite the ToF of this routine?");
+                if ui.small_button(format!("{} Confirm", egui_phosphor::CHECK)).clicked() {
+                    target.tof = self.skill_tof;
+                    target.tof_total = tof_total;
+                    target.video = self.path.clone();
+                    target.video_points = self.points.clone();
+                    self.confirm_send = false;
+                }
+                if ui.small_button("Cancel").clicked() {
+                    self.confirm_send = false;
+                }
+            });
+        } else if ui.button(format!("{} Send ToF", egui_phosphor::PAPER_PLANE_RIGHT)).clicked() {
+            self.confirm_send = true;
+        }
+    }
+
+    /// Loads the routine of the judged routine `linked_routine` falls back to, once.
+    fn load_linked_routine(&mut self, judged: &mut [Judged]) {
+        if !self.routine_id.is_empty() {
+            return;
+        }
+        if let Some(j) = judged.iter_mut().find(|j| j.video == self.path || j.id == self.send_to) {
+            let key = (j.id.clone(), j.routine_id.clone());
+            if j.routine.is_none() && !self.missing_routines.contains(&key) {
+                j.load_routine();
+                if j.routine.is_none() {
+                    self.missing_routines.insert(key);
+                }
+            }
+        }
+    }
+
+    /// The routine attached to the video, or else the one of the judged routine the ToF
+    /// was sent to, with a name for it. See `load_linked_routine`.
+    fn linked_routine<'a>(&self, judged: &'a [Judged], routines: &'a [Routine]) -> (Option<&'a Routine>, String) {
+        if let Some(routine) = routines.iter().find(|r| !self.routine_id.is_empty() && r.id == self.routine_id) {
+            return (Some(routine), routine.name.clone());
+        }
+        match judged.iter().find(|j| j.video == self.path || j.id == self.send_to) {
+            Some(j) =>
+                match &j.routine {
+                    Some(routine) => (Some(routine), format!("{} {}", j.athlete, routine.name)),
+                    None => (None, j.athlete.clone()),
+                }
+            None => (None, String::new()),
+        }
+    }
+
+    /// Time of flight of each marked take-off and landing pair.
+    fn flight_times(&self) -> Vec<f32> {
+        self.points
+            .chunks_exact(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect()
+    }
+
+    /// Skill names of the linked routine, and a name for the whole routine.
+    fn linked_names(&mut self, judged: &mut [Judged], routines: &[Routine]) -> (Vec<String>, String) {
+        self.load_linked_routine(judged);
+        let (routine, routine_name) = self.linked_routine(judged, routines);
+        let names = match routine {
+            Some(routine) => routine.skills.iter().map(|s| s.name()).collect::<Vec<String>>(),
+            None => vec![],
+        };
+        let routine_name = match routine_name.trim().is_empty() {
+            true =>
+                std::path::Path::new(&self.path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or("routine".to_owned()),
+            false => routine_name.trim().to_owned(),
+        };
+        (names, routine_name)
+    }
+
+    fn display_clips(&mut self, ui: &mut egui::Ui, judged: &mut [Judged], routines: &[Routine]) {
+        if let Some(export) = &self.clip_export {
+            if export.is_finished() {
+                self.clip_status = match self.clip_export.take().unwrap().join() {
+                    Ok(Ok(count)) => format!("Exported {} clips", count),
+                    Ok(Err(e)) => {
+                        self.error = Some(("Error exporting clips".to_owned(), e));
+                        String::new()
+                    }
+                    Err(_) => "Export failed".to_owned(),
+                };
+            }
+        }
+        let (names, routine_name) = self.linked_names(judged, routines);
+        if names.is_empty() {
+            ui.label("Attach a routine or send the ToF to a judged routine to name clips after its skills");
+        }
+        ui.horizontal(|ui| {
+            ui.label("Padding ");
+            ui.add(egui::DragValue::new(&mut self.clip_padding).speed(0.1).clamp_range(0.0..=5.0).suffix("sec"));
+        });
+        if self.clip_export.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Exporting...");
+            });
+            return;
+        }
+        let mut clips = None;
+        ui.horizontal(|ui| {
+            ui.add_enabled(self.points.len() >= 2, egui::Button::new(format!("{} Skills", egui_phosphor::FILM_STRIP)))
+                .clicked()
+                .then(|| {
+                    clips = Some(skill_clips(&self.points, &names, self.clip_padding));
+                });
+            ui.add_enabled(self.points.len() >= 2, egui::Button::new(format!("{} Whole routine", egui_phosphor::SCISSORS)))
+                .clicked()
+                .then(|| {
+                    clips = routine_clip(&self.points, &routine_name, self.clip_padding).map(|c| vec![c]);
+                });
+        });
+        if let Some(clips) = clips {
+            if let Ok(Response::Okay(folder)) = nfd2::open_pick_folder(None) {
+                let source = self.source.clone();
+                self.clip_status = String::new();
+                self.clip_export = Some(std::thread::spawn(move || export_clips(&source, &clips, &folder)));
+            }
+        }
+        if !self.clip_status.is_empty() {
+            ui.label(&self.clip_status);
+        }
+    }
+
+    fn display_strobe(&mut self, ui: &mut egui::Ui, judged: &mut [Judged], routines: &[Routine]) {
+        if let Some(export) = &self.strobe_export {
+            if export.is_finished() {
+                self.strobe_status = match self.strobe_export.take().unwrap().join() {
+                    Ok(Ok(path)) => format!("Saved {}", path.display()),
+                    Ok(Err(e)) => {
+                        self.error = Some(("Error making strobe image".to_owned(), e));
+                        String::new()
+                    }
+                    Err(_) => "Export failed".to_owned(),
+                };
+            }
+        }
+        let elements = self.points.len() / 2;
+        if elements == 0 {
+            ui.label("Mark a take-off and landing first");
+            return;
+        }
+        self.strobe_element = self.strobe_element.min(elements - 1);
+        let (names, routine_name) = self.linked_names(judged, routines);
+        let name = |i: usize| match names.get(i) {
+            Some(name) => name.clone(),
+            None => format!("Element {}", i + 1),
+        };
+        egui::ComboBox
+            ::from_label("skill")
+            .selected_text(name(self.strobe_element))
+            .show_ui(ui, |ui| {
+                for i in 0..elements {
+                    ui.selectable_value(&mut self.strobe_element, i, name(i));
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.label("Every ");
+            ui.add(egui::DragValue::new(&mut self.strobe_every).clamp_range(1..=48).suffix(" frames"));
+        });
+        ui.add(egui::Slider::new(&mut self.strobe_threshold, 10.0..=200.0).text("background threshold"))
+            .on_hover_text("raise it if the background shows up more than once, lower it if the athlete is patchy");
+        if self.strobe_export.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Rendering...");
+            });
+            return;
+        }
+        if ui.button(format!("{} Export PNG", egui_phosphor::IMAGE)).clicked() {
+            if let Ok(Response::Okay(folder)) = nfd2::open_pick_folder(None) {
+                let pair = &self.points[self.strobe_element * 2..self.strobe_element * 2 + 2];
+                let settings = StrobeSettings {
+                    start: pair[0].min(pair[1]),
+                    end: pair[0].max(pair[1]),
+                    fps: self.timeline.fps(),
+                    every: self.strobe_every,
+                    long_edge: self.long_edge,
+                    threshold: self.strobe_threshold,
+                    memory: self.cache.budget + self.textures.budget,
+                };
+                let path = folder.join(format!("{} strobe.png", file_name(&format!("{} {}", routine_name, name(self.strobe_element)))));
+                let source = self.source.clone();
+                self.strobe_status = String::new();
+                self.strobe_export = Some(std::thread::spawn(move || export_strobe(&source, &settings, &path).map(|_| path)));
+            }
+        }
+        if !self.strobe_status.is_empty() {
+            ui.label(&self.strobe_status);
+        }
+    }
+
+    pub fn display(&mut self, egui_ctx: &egui::Context, judged: &mut [Judged], routines: &[Routine]) {
         
-        let framerate =
-            (match self.textures.last() {
-                Some(a) => { a.1 }
-                None => 0.0,
-            }) / (self.textures.len() as f32);
+        self.advance();
+        self.update_frames();
+        self.update_audio();
         egui::Window
             ::new("Video")
             .id(egui::Id::new(self.id.clone()))
             .scroll2([true, true])
             
-            .min_height(match self.textures.get(self.current_frame) {
-                Some(a) => a.0.height() + 10.0,
+            .min_height(match self.texture() {
+                Some(a) => a.height() + 10.0,
                 _ => 0.0,
             })
             .show(egui_ctx, |ui| {
 
                 self.full_size = true;
+                self.display_error(ui);
                 // Show the image:
                 if self.thread.len() != 0 {
                     ui.heading("Loading...");
+                    match self.load_job.progress() {
+                        Some(progress) => {
+                            ui.add(egui::ProgressBar::new(progress).show_percentage());
+                        }
+                        None => {
+                            ui.spinner();
+                        }
+                    }
+                    ui.add_enabled(!self.load_job.cancelled(), egui::Button::new(format!("{} Cancel", egui_phosphor::X)))
+                        .clicked()
+                        .then(|| {
+                            self.load_job.cancel();
+                        });
                     if self.thread[self.thread.len() - 1].is_finished() {
                         match self.thread.pop().unwrap().join() {
-                            Ok(a) => {
-                                self.textures = a
-                                    .iter()
-                                    .map(|frame| (
-                                        Texture2D::from_rgba8(
-                                            frame.width as u16,
-                                            frame.height as u16,
-                                            &frame.data
-                                        ),
-                                        frame.timestamp,
-                                    ))
-                                    .collect();
+                            Ok(Ok((path, source, info, timeline))) => {
+                                self.decoder = Some(
+                                    Decoder::new(DecodeSettings {
+                                        source: source.clone(),
+                                        timestamps: self.timestamps,
+                                        interpolate: self.interpolate,
+                                        long_edge: self.long_edge,
+                                        sampling: self.sampling,
+                                    }, timeline.clone())
+                                );
+                                self.path = path;
+                                self.source = source;
+                                self.info = Some(info);
+                                self.timeline = timeline;
+                                let timeline = &self.timeline;
+                                self.tracking.frames_to_times(|frame| timeline.time(frame) as f64);
+                                self.seek(self.frame_at(self.start_from));
                                 self.show_video = true;
+                                if self.detect_on_open {
+                                    self.detect_on_open = false;
+                                    self.load_audio(true);
+                                }
+                            }
+                            Ok(Err(e)) if e == CANCELLED => {}
+                            Ok(Err(e)) => {
+                                self.error = Some((format!("Error opening video: {}", e), self.load_job.log_excerpt()));
+                            }
+                            Err(_) => {
+                                self.error = Some(("The video loader crashed".to_owned(), self.load_job.log_excerpt()));
                             }
-                            Err(_) => {}
                         }
                     }
                     return;
                 }
 
                 ui.separator();
-                match self.textures.len() {
-                    0 => {
+                match self.info {
+                    None => {
 
                         let mut hovered_files = vec![];
                         let mut droped_file: Option<String> = None;
@@ -189,20 +1736,20 @@ impl Video {
                         if file_input.clicked() || file_input.clicked_by(PointerButton::Secondary)  {
                             let mut path = None;
                             if file_input.clicked_by(PointerButton::Secondary) {
-                                println!("Right Clicked");
                                 egui_ctx.output_mut(|o| {
                                     path =  Some(o.copied_text.clone());
                                 });
                             }
-                            let t = self.timestamps;
-                            let start = self.start_from;
-                            let inter = self.interpolate;
-                            self.thread.push(
-                                std::thread::spawn(move || {
-                                    Self::load_textures(path, t, start, inter).unwrap()
-                                })
-                            );
+                            self.start_loading(path);
                         }
+                        ui.button(format!("{} open image sequence folder", egui_phosphor::IMAGES))
+                            .on_hover_text("numbered images, a folder can also be dropped or a glob like shots/*.png pasted")
+                            .clicked()
+                            .then(|| {
+                                if let Ok(Response::Okay(folder)) = nfd2::open_pick_folder(None) {
+                                    self.start_loading(Some(folder.display().to_string()));
+                                }
+                            });
                         
                         ui.horizontal(|ui| {
                             ui.label(format!("{} start from: ", egui_phosphor::SKIP_FORWARD));
@@ -210,23 +1757,23 @@ impl Video {
                                 egui::DragValue
                                     ::new(&mut self.start_from)
                                     .speed(0.1)
-                                    .clamp_range(0.0..=100.0)
+                                    .clamp_range(0.0..=36000.0)
                                     .suffix("sec")
                             );
                         });
-                        ui.checkbox(&mut self.timestamps, format!("{} timestamps", egui_phosphor::WATCH));
-                        ui.checkbox(&mut self.interpolate, format!("{} interpolate frames", egui_phosphor::INTERSECT_SQUARE));
+                        self.display_decode_settings(ui);
                     }
-                    _ => {
-                        let frame_data = self.textures[self.current_frame].0;
-                        let timestamp = self.textures[self.current_frame].1;
-
-                        if self.current_frame >= self.textures.len() {
+                    Some(info) => {
+                        let frame_count = self.frame_count();
+                        if self.current_frame >= frame_count {
                             self.current_frame = 0;
                         }
-                        let ratio =
-                            (frame_data.height() as f32) /
-                            (frame_data.width() as f32);
+                        let timestamp = self.time(self.current_frame);
+                        let (width, height) = match self.texture() {
+                            Some(t) => (t.width(), t.height()),
+                            None => (info.width as f32, info.height as f32),
+                        };
+                        let ratio = height / width;
                         
 
                         let r = ui.add_sized([ui.available_width(), ui.available_width()*ratio], egui::Label::new(
@@ -237,15 +1784,41 @@ impl Video {
                                 false => {
                                     format!("{} {}x{}\n",
                                     egui_phosphor::FRAME_CORNERS,
-                                    frame_data.width(),
-                                    frame_data.height()
+                                    width,
+                                    height
                                 )
                                 }
                             }
-                        ));
+                        ).sense(egui::Sense::click_and_drag()));
 
                         self.rect = [r.rect.left() , r.rect.top(), r.rect.width(), r.rect.height()];
 
+                        if self.select_roi {
+                            let pointer = r.interact_pointer_pos().map(|pos| [
+                                ((pos.x - r.rect.left()) / r.rect.width()).clamp(0.0, 1.0),
+                                ((pos.y - r.rect.top()) / r.rect.height()).clamp(0.0, 1.0),
+                            ]);
+                            if r.drag_started() {
+                                self.roi_drag = pointer;
+                            }
+                            if let (Some(start), Some(end)) = (self.roi_drag, pointer) {
+                                self.roi = Some([
+                                    start[0].min(end[0]),
+                                    start[1].min(end[1]),
+                                    start[0].max(end[0]),
+                                    start[1].max(end[1]),
+                                ]);
+                            }
+                            if r.drag_released() {
+                                self.roi_drag = None;
+                                self.select_roi = false;
+                            }
+                        } else if self.annotate_tool.is_some() {
+                            self.annotate_input(&r);
+                        } else {
+                            self.track_click(&r);
+                        }
+
                         fn point_label(p: f64, _range: &RangeInclusive<f64>) -> String {
                             format!("{p}sec")
                         }
@@ -261,6 +1834,23 @@ impl Video {
                         self.skill_tof = [0.0;10];
                         
                         self.points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                        self.load_linked_routine(judged);
+                        let (routine, _) = self.linked_routine(judged, routines);
+                        egui::ComboBox
+                            ::from_label("routine")
+                            .selected_text(match routines.iter().find(|r| !self.routine_id.is_empty() && r.id == self.routine_id) {
+                                Some(r) => r.name.clone(),
+                                None if routine.is_some() => "from judged routine".to_owned(),
+                                None => "None".to_owned(),
+                            })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.routine_id, String::new(), "None");
+                                for r in routines {
+                                    ui.selectable_value(&mut self.routine_id, r.id.clone(), &r.name);
+                                }
+                            })
+                            .response.on_hover_text("label the marked elements with its skills");
+                        let mut hover_time = None;
                         let bar = Plot::new("my_plot")
                             .show_background(true)
                             .allow_drag(false)
@@ -273,8 +1863,9 @@ impl Video {
                             .label_formatter(label_formatter)
                             .x_axis_formatter(point_label)
                             .view_aspect(1200.0)
-                            .include_x(self.textures[self.textures.len() - 1].1)
+                            .include_x(info.duration)
                             .show(ui, |plot_ui| {
+                                hover_time = plot_ui.pointer_coordinate().map(|p| p.x as f32);
                                 for (j, i) in self.points.iter().enumerate() {
                                     match j % 2 {
                                         0 => {
@@ -302,9 +1893,15 @@ impl Video {
                                         }
                                         _ => {
                                             ToF += *i - self.points[j - 1];
-                                            if j < 10 && j > 1 {
+                                            if j / 2 < 10 {
                                                 self.skill_tof[j/2] =  *i - self.points[j - 1];
                                             }
+                                            if let Some(skill) = routine.as_ref().and_then(|r| r.skills.get(j / 2)) {
+                                                plot_ui.text(
+                                                    Text::new(PlotPoint::new((self.points[j - 1] + *i) / 2.0, 1.5), skill.name())
+                                                        .color(egui::Color32::WHITE)
+                                                );
+                                            }
                                             plot_ui.polygon(
                                                 Polygon::new(
                                                     vec![
@@ -328,9 +1925,53 @@ impl Video {
                                     }
                                 }
 
+                                for fault in self.faults.iter() {
+                                    plot_ui.points(
+                                        Points::new(vec![[fault.time as f64, 2.5]])
+                                            .shape(MarkerShape::Down)
+                                            .filled(true)
+                                            .radius(5.0)
+                                            .color(egui::Color32::from_rgb(255, 0, 255))
+                                            .name(format!("{} -{:.1}", fault.category.name(), fault.deduction))
+                                    );
+                                }
+
+                                for suggestion in self.suggestions.iter() {
+                                    plot_ui.vline(
+                                        VLine::new(suggestion.time)
+                                            .style(LineStyle::Dashed { length: 4.0 })
+                                            .color(egui::Color32::from_rgba_unmultiplied(255, 165, 0, (80.0 + suggestion.confidence * 175.0) as u8))
+                                            .name("suggested")
+                                    );
+                                }
+
+                                if let Some((start, end)) = self.loop_marks {
+                                    plot_ui.polygon(
+                                        Polygon::new(
+                                            vec![
+                                                [self.timeline.time(start) as f64, 0.0] as _,
+                                                [self.timeline.time(end) as f64, 0.0] as _,
+                                                [self.timeline.time(end) as f64, 3.0] as _,
+                                                [self.timeline.time(start) as f64, 3.0] as _
+                                            ]
+                                        )
+                                            .fill_alpha(0.2)
+                                            .color(egui::Color32::from_rgb(0, 0, 255))
+                                            .width(0.0)
+                                            .name("loop")
+                                    );
+                                }
+
+                                if let Some(time) = hover_time {
+                                    plot_ui.vline(
+                                        VLine::new(time)
+                                            .color(egui::Color32::GRAY)
+                                    );
+                                }
+
                                 plot_ui.vline(
                                     VLine::new(timestamp)
-                                        .highlight(self.current_frame == self.drag_one)
+                                        .highlight(true)
                                         .color(egui::Color32::from_rgb(0, 0, 255))
                                 );
 
@@ -345,56 +1986,117 @@ impl Video {
                             }
                         });
 
-                        match bar.hover_pos() {
-                            Some(pos) => {
-                                ui.separator();
-                                self.current_frame = (
-                                    (((pos.x - bar.rect.left()) / bar.rect.width()) *
-                                        (self.textures.len() as f32)) as usize
-                                ).clamp(0, self.textures.len() - 1);
-                                if self.add_points {
-                                    let mut delete = false;
-                                    for pt in 0..self.points.len() {
-                                        if self.points.len() <= pt {
-                                            break;
+                        match &self.audio {
+                            Some(envelope) => {
+                                let peak = envelope.iter().cloned().fold(f32::EPSILON, f32::max);
+                                let step = (envelope.len() / 2000).max(1);
+                                let waveform: PlotPoints = envelope
+                                    .chunks(step)
+                                    .enumerate()
+                                    .map(|(i, w)| [
+                                        ((i * step) as f64) / (ENVELOPE_RATE as f64),
+                                        (w.iter().cloned().fold(0.0, f32::max) / peak) as f64,
+                                    ])
+                                    .collect();
+                                Plot::new("audio")
+                                    .show_background(true)
+                                    .allow_drag(false)
+                                    .allow_zoom(false)
+                                    .allow_scroll(false)
+                                    .include_x(0.0)
+                                    .include_x(info.duration)
+                                    .include_y(0.0)
+                                    .include_y(1.0)
+                                    .show_y(false)
+                                    .show_axes([false, false])
+                                    .height(40.0)
+                                    .show(ui, |plot_ui| {
+                                        plot_ui.line(Line::new(waveform).fill(0.0).color(egui::Color32::GRAY).name("audio"));
+                                        for suggestion in self.suggestions.iter() {
+                                            plot_ui.vline(
+                                                VLine::new(suggestion.time)
+                                                    .style(LineStyle::Dashed { length: 4.0 })
+                                                    .color(egui::Color32::from_rgb(255, 165, 0))
+                                            );
                                         }
-                                        if
-                                            (
-                                                self.points[pt] -
-                                                timestamp
-                                            ).abs() < 0.05
-                                        {
-                                            delete = true;
-                                            ui.label("remove point");
-                                            egui_ctx.output_mut(|o| {
-                                                o.cursor_icon = egui::CursorIcon::NotAllowed;
-                                            });
-                                            if ui.input(|i| i.pointer.any_click()) {
-                                                self.points.remove(pt);
-                                            }
+                                        plot_ui.vline(VLine::new(timestamp).color(egui::Color32::from_rgb(0, 0, 255)));
+                                    });
+                            }
+                            None => {
+                                if self.audio_thread.is_none() {
+                                    ui.small_button(format!("{} show waveform", egui_phosphor::WAVEFORM)).clicked().then(|| {
+                                        self.load_audio(false);
+                                    });
+                                }
+                            }
+                        }
+
+                        if let Some(time) = bar.hover_pos().and(hover_time) {
+                            if let Some(k) = self.points.chunks_exact(2).position(|p| time >= p[0] && time <= p[1]) {
+                                let tof = self.flight_times()[k];
+                                egui::show_tooltip_at_pointer(egui_ctx, egui::Id::new(format!("{} element", self.id)), |ui| {
+                                    match routine.as_ref().and_then(|r| r.skills.get(k)) {
+                                        Some(skill) => {
+                                            ui.strong(skill.name());
+                                            ui.label(format!("{}  DD {:.1}", skill.notation(), skill.diff()));
+                                        }
+                                        None => {
+                                            ui.strong(format!("Element {}", k + 1));
                                         }
                                     }
-                                    if !delete {
-                                        ui.label("Add point");
+                                    ui.label(format!("ToF {:.3}sec ({:.2}m)", tof, flight_height(tof)));
+                                });
+                            }
+                        }
+                        if let Some(time) = bar.hover_pos().and(hover_time) {
+                            ui.separator();
+                            let frame = self.frame_at(time);
+                            let time = self.time(frame);
+                            if self.add_points {
+                                match self.points.iter().position(|p| (p - time).abs() < 0.05) {
+                                    Some(pt) => {
+                                        ui.label("remove point");
+                                        egui_ctx.output_mut(|o| {
+                                            o.cursor_icon = egui::CursorIcon::NotAllowed;
+                                        });
                                         if ui.input(|i| i.pointer.any_click()) {
-                                            self.points.push(timestamp);
+                                            self.points.remove(pt);
                                         }
                                     }
-                                } else {
-                                    ui.label("add point");
-                                    if ui.input(|i| i.pointer.any_click()) {
-                                        self.drag_one = self.current_frame;
+                                    None => {
+                                        ui.label("Add point");
+                                        if ui.input(|i| i.pointer.any_click()) {
+                                            self.points.push(time);
+                                        }
                                     }
                                 }
-                            }
-                            _ => {
-                                self.current_frame = self.drag_one.clamp(
-                                    0,
-                                    self.textures.len() - 1
-                                );
+                            } else {
+                                ui.label(format!("move player to {:.2}sec", time));
+                                if ui.input(|i| i.pointer.primary_down()) {
+                                    self.seek(frame);
+                                }
                             }
                         }
 
+                        self.display_transport(ui);
+
+                        ui.collapsing(format!("{} Track hips", egui_phosphor::PERSON_SIMPLE), |ui| {
+                            self.display_tracking(ui);
+                        });
+
+                        ui.collapsing(format!("{} Annotate", egui_phosphor::PENCIL_LINE), |ui| {
+                            self.display_annotations(ui);
+                        });
+
+                        ui.collapsing(format!("{} Faults", egui_phosphor::FLAG), |ui| {
+                            self.display_faults(ui, judged, routines);
+                        });
+
+                        ui.collapsing(format!("{} Detect bounces", egui_phosphor::MAGIC_WAND), |ui| {
+                            self.display_detection(ui);
+                        });
+
+                        self.tof_total = ToF;
 
                         ui.separator();
                         ui.label(
@@ -405,6 +2107,7 @@ impl Video {
                         );
                         ui.horizontal(|ui| {
                             ui.label(format!("total ToF: {:.2}", ToF));
+                            ui.label(format!("peak height: {:.2}m", flight_height(self.skill_tof.iter().cloned().fold(0.0, f32::max))));
                             if
                                 ui
                                     .small_button(egui_phosphor::CLIPBOARD_TEXT)
@@ -419,10 +2122,13 @@ impl Video {
 
                         ui.horizontal(|ui| {
                             ui.collapsing("Individual ToF", |ui| {
-                                for (i, j) in self.skill_tof.iter().enumerate() {
-                                    ui.label(format!("{}: {:.2}", i, j));
+                                let (routine, _) = self.linked_routine(judged, routines);
+                                for (k, tof) in self.flight_times().into_iter().enumerate() {
+                                    match routine.and_then(|r| r.skills.get(k)) {
+                                        Some(skill) => ui.label(format!("{} {}: {:.2} ({:.2}m)", k + 1, skill.name(), tof, flight_height(tof))),
+                                        None => ui.label(format!("{}: {:.2} ({:.2}m)", k + 1, tof, flight_height(tof))),
+                                    };
                                 }
-                            
                             });
                             if
                                 ui
@@ -436,10 +2142,35 @@ impl Video {
                             }
                         });
 
+                        ui.collapsing(format!("{} Send to judged routine", egui_phosphor::PAPER_PLANE_RIGHT), |ui| {
+                            self.display_send(ui, judged);
+                        });
+
+                        ui.collapsing(format!("{} Export clips", egui_phosphor::FILM_STRIP), |ui| {
+                            self.display_clips(ui, judged, routines);
+                        });
+
+                        ui.collapsing(format!("{} Strobe image", egui_phosphor::PERSON_SIMPLE_RUN), |ui| {
+                            self.display_strobe(ui, judged, routines);
+                        });
+
                         ui.separator();
                         // number input
                         ui.label(format!("Time: {}", timestamp));
                         ui.checkbox(&mut self.show_video, "Render Video");
+                        ui.collapsing(format!("{} Decode settings", egui_phosphor::GEAR), |ui| {
+                            ui.label(format!("{} frames, {:.1}fps", self.timeline.len(), self.timeline.fps()));
+                            ui.label(format!(
+                                "{}MB decoded, {}MB of textures",
+                                self.cache.used() / 1024 / 1024,
+                                self.textures.used() / 1024 / 1024
+                            ));
+                            self.display_decode_settings(ui);
+                            ui.button("Apply").clicked().then(|| {
+                                self.reload();
+                            });
+                        });
+                        self.handle_keys(ui);
                     }
                 }
 
@@ -459,22 +2190,107 @@ impl Video {
     pub fn new() -> Video {
         Video {
             skill_tof: [0.0;10],
+            tof_total: 0.0,
             full_size: true,
             add_points: false,
             path: String::from(""),
+            source: Source::File(String::new()),
+            image_fps: IMAGE_FPS,
             open: true,
-            textures: Vec::new(),
+            info: None,
+            decoder: None,
+            cache: FrameCache::new(DEFAULT_BUDGET / 3 * 2),
+            textures: TextureCache::new(DEFAULT_BUDGET / 3),
+            shown: None,
             current_frame: 0,
             show_video: true,
             kill: false,
             rect: [0.0, 0.0, 0.0, 0.0],
-            drag_one: 0,
+            playing: false,
+            reverse: false,
+            speed: 1.0,
+            play_time: 0.0,
+            loop_marks: None,
             timestamps: false,
             points: Vec::new(),
             start_from: 0.0,
             thread: vec![],
+            timeline: Timeline::Fixed { fps: FPS, frames: 0 },
+            long_edge: Some(LONG_EDGE),
+            sampling: Sampling::default(),
             interpolate: false,
-            id: UNIX_EPOCH.elapsed().unwrap().as_millis().to_string().replace(".", ""),
+            roi: None,
+            select_roi: false,
+            roi_drag: None,
+            show_roi: true,
+            suggestions: vec![],
+            analysis: None,
+            send_to: String::new(),
+            routine_id: String::new(),
+            confirm_send: false,
+            clip_padding: 0.5,
+            clip_export: None,
+            clip_status: String::new(),
+            missing_routines: HashSet::new(),
+            strobe_element: 0,
+            strobe_every: 6,
+            strobe_threshold: STROBE_THRESHOLD,
+            strobe_export: None,
+            strobe_status: String::new(),
+            tracking: Tracking::default(),
+            annotations: vec![],
+            annotate_tool: None,
+            annotation_draft: vec![],
+            annotation_pointer: None,
+            annotation_export: None,
+            annotation_status: String::new(),
+            faults: vec![],
+            fault_category: FaultCategory::BentLegs,
+            fault_deduction: 0.1,
+            fault_note: String::new(),
+            fault_judge: 0,
+            confirm_faults: false,
+            track_mode: TrackMode::Off,
+            track_step: 4,
+            calibration_start: None,
+            calibration_metres: FRAME_LENGTH,
+            show_track: true,
+            audio: None,
+            audio_thread: None,
+            audio_detect: false,
+            detect_on_open: false,
+            load_job: Job::default(),
+            error: None,
+            id: new_id(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_reopen_where_they_were_left() {
+        let mut video = Video::new();
+        video.path = "missing.mp4".to_owned();
+        video.start_from = 3.5;
+        video.points = vec![1.0, 2.0];
+        let mut bytes = vec![];
+        savefile::save(&mut bytes, 8, &video.session()).unwrap();
+        let session: VideoSession = savefile::load(&mut &bytes[..], 8).unwrap();
+
+        let restored = Video::from_session(session);
+        assert_eq!(restored.id(), video.id());
+        assert_eq!(restored.session().start_from, 3.5);
+        assert_eq!(restored.session().points, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn recordings_from_one_scan_get_their_own_ids() {
+        let settings = WatchSettings::default();
+        let videos = ["clip1.mp4", "clip2.mp4", "clip3.mp4"]
+            .map(|path| Video::watched(path.to_owned(), &settings));
+        let ids = videos.iter().map(|v| v.id()).collect::<HashSet<&str>>();
+        assert_eq!(ids.len(), 3);
+    }
+}
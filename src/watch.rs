@@ -0,0 +1,157 @@
+use std::{ collections::{ HashMap, HashSet }, path::PathBuf, time::{ Duration, Instant } };
+
+const VIDEO_EXTENSIONS: [&str; 8] = ["mp4", "mov", "m4v", "avi", "mkv", "webm", "mts", "3gp"];
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, Savefile, PartialEq)]
+pub struct WatchSettings {
+    pub enabled: bool,
+    pub folder: String,
+    /// Routine attached to new videos, empty for none.
+    pub routine_id: String,
+    /// Judged routine new videos are linked to, empty for none.
+    pub judged_id: String,
+    /// Suggest take-offs and landings from the audio as soon as a video opens.
+    pub analyse: bool,
+}
+
+/// Polls `settings.folder` for recordings that appear while the app runs.
+pub struct FolderWatch {
+    pub settings: WatchSettings,
+    /// Folder `seen` was filled from.
+    watching: String,
+    seen: HashSet<PathBuf>,
+    /// New files and their size at the last scan, opened once the size stops changing
+    /// so half-synced files are left alone.
+    syncing: HashMap<PathBuf, u64>,
+    last_scan: Instant,
+    pub imported: usize,
+    pub error: String,
+}
+
+fn videos_in(folder: &str) -> Result<Vec<(PathBuf, u64)>, String> {
+    Ok(
+        std::fs
+            ::read_dir(folder)
+            .map_err(|e| format!("{}: {}", folder, e))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                match entry.path().extension() {
+                    Some(ext) => VIDEO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()),
+                    None => false,
+                }
+            })
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                match metadata.is_file() {
+                    true => Some((entry.path(), metadata.len())),
+                    false => None,
+                }
+            })
+            .collect()
+    )
+}
+
+impl FolderWatch {
+    pub fn new() -> FolderWatch {
+        FolderWatch {
+            settings: WatchSettings::default(),
+            watching: String::new(),
+            seen: HashSet::new(),
+            syncing: HashMap::new(),
+            last_scan: Instant::now(),
+            imported: 0,
+            error: String::new(),
+        }
+    }
+
+    /// Paths of recordings that finished arriving since the last call.
+    pub fn poll(&mut self) -> Vec<String> {
+        if !self.settings.enabled || self.settings.folder.is_empty() {
+            self.watching = String::new();
+            return vec![];
+        }
+        if self.last_scan.elapsed() < SCAN_INTERVAL {
+            return vec![];
+        }
+        self.last_scan = Instant::now();
+        let files = match videos_in(&self.settings.folder) {
+            Ok(files) => files,
+            Err(e) => {
+                self.error = e;
+                return vec![];
+            }
+        };
+        self.error = String::new();
+        // what is already there when watching starts is not new
+        if self.watching != self.settings.folder {
+            self.watching = self.settings.folder.clone();
+            self.seen = files.into_iter().map(|(path, _)| path).collect();
+            self.syncing.clear();
+            return vec![];
+        }
+        let mut ready = vec![];
+        for (path, size) in files {
+            if self.seen.contains(&path) {
+                continue;
+            }
+            match self.syncing.insert(path.clone(), size) {
+                Some(previous) if previous == size && size > 0 => {
+                    self.syncing.remove(&path);
+                    self.seen.insert(path.clone());
+                    ready.push(path.display().to_string());
+                }
+                _ => {}
+            }
+        }
+        self.imported += ready.len();
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Polls as if the scan interval had passed.
+    fn scan(watch: &mut FolderWatch) -> Vec<String> {
+        watch.last_scan = Instant::now() - SCAN_INTERVAL;
+        watch.poll()
+    }
+
+    #[test]
+    fn new_files_are_reported_once_synced() {
+        let folder = std::env::temp_dir().join(format!("watch-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("old.mp4"), b"old").unwrap();
+
+        let mut watch = FolderWatch::new();
+        watch.settings.folder = folder.display().to_string();
+        assert!(scan(&mut watch).is_empty());
+        watch.settings.enabled = true;
+        // the first scan only takes note of what is already there
+        assert!(scan(&mut watch).is_empty());
+        assert!(watch.seen.contains(&folder.join("old.mp4")));
+
+        let new = folder.join("new.MOV");
+        std::fs::write(&new, b"half").unwrap();
+        std::fs::write(folder.join("notes.txt"), b"not a video").unwrap();
+        std::fs::write(folder.join("empty.mp4"), b"").unwrap();
+        assert!(scan(&mut watch).is_empty());
+        // still growing
+        std::fs::write(&new, b"half and the rest").unwrap();
+        assert!(scan(&mut watch).is_empty());
+        // not scanned again before the interval
+        assert!(watch.poll().is_empty());
+        assert_eq!(scan(&mut watch), vec![new.display().to_string()]);
+        assert!(scan(&mut watch).is_empty());
+        assert_eq!(watch.imported, 1);
+        // empty files are never ready
+        assert!(watch.syncing.contains_key(&folder.join("empty.mp4")));
+
+        std::fs::remove_dir_all(&folder).unwrap();
+        assert!(scan(&mut watch).is_empty());
+        assert!(!watch.error.is_empty());
+    }
+}